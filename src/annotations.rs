@@ -0,0 +1,59 @@
+/// Manifest fields declared in the header comment of main.lua:
+///
+/// ```lua
+/// -- @id com.example.MyTool
+/// -- @name My Tool
+/// -- @description Does things,
+/// --   continued on the next line.
+/// ```
+///
+/// Returns `(element name, value)` pairs in the order they appear. Parsing stops
+/// at the first line that is not a `--` comment; unknown tags are ignored.
+pub fn parse(lua: &str) -> Vec<(&'static str, String)> {
+    let mut fields: Vec<(&'static str, String)> = Vec::new();
+    let mut current: Option<usize> = None;
+    for line in lua.lines() {
+        let line = line.trim();
+        if line.is_empty() && fields.is_empty() {
+            continue;
+        }
+        let Some(comment) = line.strip_prefix("--") else {
+            break;
+        };
+        let comment = comment.trim_start_matches('-').trim();
+        if let Some(tagged) = comment.strip_prefix('@') {
+            let (tag, value) = tagged
+                .split_once(char::is_whitespace)
+                .unwrap_or((tagged, ""));
+            current = element(tag).map(|name| {
+                fields.push((name, value.trim().to_string()));
+                fields.len() - 1
+            });
+        } else if comment.is_empty() {
+            current = None;
+        } else if let Some(i) = current {
+            let value = &mut fields[i].1;
+            if !value.is_empty() {
+                value.push(' ');
+            }
+            value.push_str(comment);
+        }
+    }
+    fields
+}
+
+fn element(tag: &str) -> Option<&'static str> {
+    Some(match tag.to_ascii_lowercase().as_str() {
+        "id" => "Id",
+        "name" => "Name",
+        "author" => "Author",
+        "version" => "Version",
+        "description" => "Description",
+        "api_version" | "apiversion" => "ApiVersion",
+        "category" => "Category",
+        "homepage" => "Homepage",
+        "icon" => "Icon",
+        "platform" => "Platform",
+        _ => return None,
+    })
+}
@@ -0,0 +1,162 @@
+use crate::lua::identifier;
+use crate::renoise::ApiVersion;
+use full_moon::ast::{Call, Expression, Index, Prefix, Suffix, Var};
+use full_moon::ast::{FunctionCall, LocalAssignment, VarExpression};
+use full_moon::tokenizer::TokenReference;
+use full_moon::visitors::Visitor;
+use full_moon::{LuaVersion, parse_fallible};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+const INDEX: &str = include_str!("data/renoise_api.toml");
+
+/// The ApiVersion each listed part of the Renoise scripting API came with,
+/// see `data/renoise_api.toml`.
+#[derive(Deserialize)]
+struct ApiIndex {
+    since: BTreeMap<String, ApiVersion>,
+}
+
+fn index() -> &'static ApiIndex {
+    static PARSED: OnceLock<ApiIndex> = OnceLock::new();
+    PARSED.get_or_init(|| toml::from_str(INDEX).expect("the bundled API index is valid TOML"))
+}
+
+/// Uses of the Renoise API in `code` that are newer than `api_version`, as
+/// `<name>:<line>:<column>: <message>`. API parts reached from `renoise`, like
+/// `renoise.song().selected_phrase`, are recognized, also through locals set
+/// to such a path (`local song = renoise.song()`). Files that don't parse are
+/// left to the syntax check.
+pub fn newer_api_uses(name: &str, code: &str, api_version: ApiVersion) -> Vec<String> {
+    let code = code.strip_prefix('\u{feff}').unwrap_or(code);
+    let Ok(ast) = parse_fallible(code, LuaVersion::lua51()).into_result() else {
+        return Vec::new();
+    };
+    let mut uses = ApiUses {
+        api_version,
+        aliases: HashMap::new(),
+        found: BTreeMap::new(),
+    };
+    uses.visit_ast(&ast);
+    uses.found
+        .into_iter()
+        .map(|((line, column), (path, since))| {
+            format!(
+                "{name}:{line}:{column}: `{path}` needs ApiVersion {since}, the manifest declares {api_version}"
+            )
+        })
+        .collect()
+}
+
+struct ApiUses {
+    api_version: ApiVersion,
+    /// Locals holding an API path, by name. Scopes aren't told apart.
+    aliases: HashMap<String, String>,
+    /// Newer API paths found, with the ApiVersion they need, by position.
+    found: BTreeMap<(usize, usize), (String, ApiVersion)>,
+}
+
+impl ApiUses {
+    /// The API path `prefix` and `suffixes` spell, if they start at `renoise`
+    /// or an alias of it, reporting the first part that is too new.
+    fn check<'a>(
+        &mut self,
+        prefix: &Prefix,
+        suffixes: impl Iterator<Item = &'a Suffix>,
+    ) -> Option<String> {
+        let Prefix::Name(root) = prefix else {
+            return None;
+        };
+        let mut path = self.root_path(root)?;
+        for suffix in suffixes {
+            let member = match suffix {
+                Suffix::Index(Index::Dot { name, .. }) => Some(('.', name)),
+                Suffix::Call(Call::MethodCall(call)) => Some((':', call.name())),
+                _ => None,
+            };
+            if let Some((separator, name)) = member {
+                path.push(separator);
+                path.push_str(identifier(name).unwrap_or_default());
+                if let Some(&since) = index().since.get(&path)
+                    && since > self.api_version
+                {
+                    let at = name.start_position();
+                    self.found
+                        .insert((at.line(), at.character()), (path, since));
+                    return None;
+                }
+            }
+            match suffix {
+                Suffix::Index(Index::Brackets { .. }) => path.push_str("[]"),
+                Suffix::Call(_) => path.push_str("()"),
+                _ => {}
+            }
+        }
+        Some(path)
+    }
+
+    fn root_path(&self, root: &TokenReference) -> Option<String> {
+        match identifier(root)? {
+            "renoise" => Some("renoise".to_string()),
+            name => self.aliases.get(name).cloned(),
+        }
+    }
+}
+
+impl Visitor for ApiUses {
+    fn visit_local_assignment(&mut self, local: &LocalAssignment) {
+        for (name, value) in local.names().iter().zip(local.expressions().iter()) {
+            let path = match value {
+                Expression::FunctionCall(call) => self.check(call.prefix(), call.suffixes()),
+                Expression::Var(Var::Expression(var)) => self.check(var.prefix(), var.suffixes()),
+                Expression::Var(Var::Name(root)) => self.root_path(root),
+                _ => None,
+            };
+            let name = identifier(name).unwrap_or_default().to_string();
+            match path {
+                Some(path) => self.aliases.insert(name, path),
+                None => self.aliases.remove(&name),
+            };
+        }
+    }
+
+    fn visit_function_call(&mut self, call: &FunctionCall) {
+        self.check(call.prefix(), call.suffixes());
+    }
+
+    fn visit_var_expression(&mut self, var: &VarExpression) {
+        self.check(var.prefix(), var.suffixes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_newer_api_through_locals() {
+        let code = "local vb = renoise.ViewBuilder()\nlocal view = vb:canvas {}\n";
+        let uses = newer_api_uses("main.lua", code, ApiVersion::new(6, 1));
+        assert_eq!(
+            uses,
+            [
+                "main.lua:2:17: `renoise.ViewBuilder():canvas` needs ApiVersion 6.2, the manifest declares 6.1"
+            ]
+        );
+        assert!(newer_api_uses("main.lua", code, ApiVersion::new(6, 2)).is_empty());
+    }
+
+    #[test]
+    fn flags_newer_api_in_paths() {
+        let code = "print(renoise.song().instruments[1].phrases[2].script)\n";
+        let uses = newer_api_uses("main.lua", code, ApiVersion::new(3, 0));
+        assert_eq!(uses.len(), 1);
+        assert!(uses[0].contains("`renoise.song().instruments[].phrases` needs ApiVersion 4"));
+    }
+
+    #[test]
+    fn index_parses() {
+        assert!(index().since.values().any(|v| *v == ApiVersion::new(6, 2)));
+    }
+}
@@ -0,0 +1,1145 @@
+use crate::changelog::changelog_path;
+use crate::config::{Settings, SymlinkPolicy};
+use crate::git::git;
+use crate::manifest::{decode, parse_manifest};
+use crate::placeholders::BuildInfo;
+use crate::transform::Pipeline;
+use crate::version::{parse_version, split_pre};
+use ignore::WalkBuilder;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{self, AtomicUsize};
+use std::thread;
+use unicode_normalization::UnicodeNormalization;
+use zip::CompressionMethod;
+use zip::write::FileOptions;
+
+/// Prefix for in-progress archives (and the staging folders of older versions),
+/// so `rnplug clean` can spot leftovers from interrupted runs.
+pub const TEMP_PREFIX: &str = ".rnplug-tmp-";
+
+/// Patterns in gitignore syntax, in the project root, for files never to package.
+pub const IGNORE_FILE: &str = ".xrnxignore";
+
+/// Entry listing every other file of the archive with its size and SHA-256, so an
+/// installed copy can be checked against it.
+pub const FILE_LIST: &str = "files.json";
+
+/// One file in [`FILE_LIST`].
+#[derive(Serialize)]
+pub struct ListedFile {
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// A file that goes into the archive.
+pub struct SourceFile {
+    /// Entry name inside the archive.
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// The project files packaged next to the manifest: `.lua` files and configured
+/// assets anywhere in the tree, keeping their directory structure, the README,
+/// the changelog and, unless disabled, the license file. Hidden entries (`.git`,
+/// staging folders), OS and editor [junk](is_junk), [tests](is_test) unless
+/// `include_tests` is set, the release directory, in-progress archives,
+/// [`Settings::exclude`] and anything matched by [`IGNORE_FILE`] or, unless
+/// disabled, `.gitignore` are left out. Symlinks are handled as
+/// [`Settings::symlinks`] says. With `warn`, skipped symlinks and junk are
+/// reported. Entry names follow [`Settings::paths`].
+pub fn collect_sources(
+    root: &Path,
+    settings: &Settings,
+    warn: bool,
+) -> io::Result<Vec<SourceFile>> {
+    let ignore = ignore_rules(root)?;
+    // the release directory may be spelled differently, e.g. given with --output
+    let release_dir = settings.release_dir(root);
+    let release_canonical = release_dir.canonicalize().ok();
+    let gitignore = settings.gitignore != Some(false);
+    let symlinks = settings.symlinks.unwrap_or_default();
+    let skip_tests = settings.include_tests != Some(true);
+    let mut walk = WalkBuilder::new(root);
+    walk.standard_filters(false)
+        // hidden files are dropped below, after junk like .DS_Store is counted
+        .hidden(false)
+        .parents(gitignore)
+        .git_ignore(gitignore)
+        .git_exclude(gitignore)
+        .require_git(false)
+        .follow_links(symlinks == SymlinkPolicy::Follow)
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            let name = entry.file_name().to_string_lossy();
+            let skipped_dir = is_dir
+                && ((entry.depth() > 0 && name.starts_with('.'))
+                    || (skip_tests && matches!(name.as_ref(), "spec" | "tests"))
+                    || entry.path() == release_dir
+                    || release_canonical
+                        .as_ref()
+                        .is_some_and(|dir| entry.path().canonicalize().is_ok_and(|p| p == *dir)));
+            !skipped_dir
+                && !name.starts_with(TEMP_PREFIX)
+                && !ignore.matched(entry.path(), is_dir).is_ignore()
+        });
+
+    let assets = settings.assets.clone().unwrap_or_default();
+    let readme = readme_path(root);
+    let license = license_path(root).filter(|_| settings.license != Some(false));
+    let changelog = changelog_path(root);
+    let mut files = Vec::new();
+    let mut junk = Vec::new();
+    for entry in walk.build() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) if is_loop(&e) => {
+                if warn {
+                    eprintln!("warning: {e}, skipping it");
+                }
+                continue;
+            }
+            Err(e) => return Err(io::Error::other(e)),
+        };
+        let path = entry.path();
+        if entry.path_is_symlink() {
+            match symlinks {
+                SymlinkPolicy::Follow => {}
+                SymlinkPolicy::Skip => {
+                    if warn {
+                        eprintln!("warning: skipping symlink {}", path.display());
+                    }
+                    continue;
+                }
+                SymlinkPolicy::Error => {
+                    return Err(io::Error::other(format!(
+                        "{} is a symlink, set `symlinks = \"follow\"` or \"skip\" in rnplug.toml",
+                        path.display()
+                    )));
+                }
+            }
+        }
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        if entry.depth() == 0
+            || entry.file_type().is_some_and(|t| t.is_dir())
+            || settings.exclude.iter().any(|excluded| excluded == relative)
+        {
+            continue;
+        }
+        if is_junk(relative) {
+            junk.push(entry_name(relative));
+            continue;
+        }
+        let file_name = entry.file_name().to_string_lossy();
+        if file_name.starts_with('.') || (skip_tests && is_test(&file_name)) {
+            continue;
+        }
+        let archived = settings.archive_name(relative);
+        let name = if readme.as_deref() == Some(path) {
+            "README.md".to_string()
+        } else if license.as_deref() == Some(path)
+            || changelog.as_deref() == Some(path)
+            || archived != Path::new("manifest.xml")
+                && archived != Path::new(FILE_LIST)
+                && (path.extension().is_some_and(|e| e == "lua") || assets.matches(relative))
+        {
+            entry_name(&archived)
+        } else {
+            continue;
+        };
+        files.push(SourceFile {
+            name,
+            path: path.to_path_buf(),
+        });
+    }
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    if warn && !junk.is_empty() {
+        junk.sort();
+        eprintln!("Skipped {} junk file(s): {}", junk.len(), junk.join(", "));
+    }
+    Ok(files)
+}
+
+fn entry_name(relative: &Path) -> String {
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+/// Test suites that don't belong in a release: `*_spec.lua` and `*_test.lua`
+/// files here, `spec/` and `tests/` folders in [`collect_sources`].
+fn is_test(file_name: &str) -> bool {
+    file_name.ends_with("_spec.lua") || file_name.ends_with("_test.lua")
+}
+
+/// Files operating systems and editors leave behind, never packaged whatever
+/// the include rules say: Finder and Explorer metadata, `__MACOSX` folders and
+/// backup or swap files.
+fn is_junk(relative: &Path) -> bool {
+    let name = relative.file_name().unwrap_or_default().to_string_lossy();
+    let in_macosx = relative.components().any(|c| c.as_os_str() == "__MACOSX");
+    in_macosx
+        || [".DS_Store", "Thumbs.db", "desktop.ini"]
+            .iter()
+            .any(|junk| name.eq_ignore_ascii_case(junk))
+        || name.starts_with("._")
+        || name.starts_with(".#")
+        || name.ends_with('~')
+        || (name.starts_with('#') && name.ends_with('#'))
+        || [".bak", ".swp", ".swo", ".tmp", ".orig"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+}
+
+/// Whether walking failed on a symlink pointing back at one of its parents.
+fn is_loop(e: &ignore::Error) -> bool {
+    match e {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::WithPath { err, .. } | ignore::Error::WithDepth { err, .. } => is_loop(err),
+        _ => false,
+    }
+}
+
+/// The rules of the project's [`IGNORE_FILE`], matching nothing if there is none.
+fn ignore_rules(root: &Path) -> io::Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    let path = root.join(IGNORE_FILE);
+    if path.is_file()
+        && let Some(e) = builder.add(&path)
+    {
+        return Err(io::Error::other(format!("invalid {IGNORE_FILE}: {e}")));
+    }
+    builder.build().map_err(io::Error::other)
+}
+
+/// `readme.md` or `README.md` in `root`, whichever exists.
+pub fn readme_path(root: &Path) -> Option<PathBuf> {
+    ["readme.md", "README.md"]
+        .into_iter()
+        .map(|name| root.join(name))
+        .find(|path| path.exists())
+}
+
+/// `LICENSE`, `LICENSE.md` or `COPYING` in `root`, whichever exists first.
+pub fn license_path(root: &Path) -> Option<PathBuf> {
+    ["LICENSE", "LICENSE.md", "COPYING"]
+        .into_iter()
+        .map(|name| root.join(name))
+        .find(|path| path.is_file())
+}
+
+/// SHA-256 of every file that would be packaged right now, manifest included.
+pub fn source_hashes(
+    root: &Path,
+    settings: &Settings,
+    manifest: &Path,
+) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+    let mut hashes = BTreeMap::new();
+    for file in collect_sources(root, settings, false)? {
+        hashes.insert(file.name, sha256_file(&file.path)?);
+    }
+    hashes.insert("manifest.xml".to_string(), sha256_file(manifest)?);
+    Ok(hashes)
+}
+
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Zips the tool sources in `root`, plus `extra` files, into `out`, running them
+/// through `transforms`, and returns what was written. Unless disabled, a
+/// [`FILE_LIST`] is added last.
+///
+/// The same sources always give the same bytes: entries are sorted, and their
+/// timestamps come from `SOURCE_DATE_EPOCH` or are fixed at 1980-01-01.
+pub fn zip_sources(
+    root: &Path,
+    settings: &Settings,
+    manifest: &[u8],
+    transforms: &Pipeline,
+    extra: &[SourceFile],
+    out: &Path,
+    verbose: bool,
+) -> Result<Vec<ListedFile>, Box<dyn std::error::Error>> {
+    let sources = collect_sources(root, settings, true)?;
+    let mut sources: Vec<&SourceFile> = sources.iter().chain(extra).collect();
+    sources.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.path.cmp(&b.path)));
+    // e.g. a platform file that is also an asset
+    sources.dedup_by(|a, b| a.name == b.name && a.path == b.path);
+    let names = entry_names(&sources)?;
+    if let Some(generated) = &settings.build_constants
+        && names.contains(generated)
+    {
+        return Err(format!(
+            "{generated} is generated for build_constants, but the project has a file packaged as {generated}"
+        )
+        .into());
+    }
+
+    let file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(out)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let method = if settings.fast == Some(true) {
+        CompressionMethod::Stored
+    } else {
+        CompressionMethod::Deflated
+    };
+    let options = FileOptions::default()
+        .compression_method(method)
+        .last_modified_time(source_date()?)
+        .unix_permissions(0o644);
+
+    let mut jobs = Vec::new();
+    for (source, name) in sources.into_iter().zip(names) {
+        let options = if settings.keep_mtimes == Some(true) {
+            options.last_modified_time(modified_time(&source.path)?)
+        } else {
+            options
+        };
+        jobs.push((source, name, options));
+    }
+    let compressed = compress_small(&jobs, transforms)?;
+    let mut listed = Vec::new();
+    let mut dirs = BTreeSet::new();
+    for ((source, name, file_options), compressed) in jobs.iter().zip(compressed) {
+        add_parent_dirs(&mut zip, &mut dirs, name, options, verbose)?;
+        if verbose {
+            eprintln!("  adding {name}");
+        }
+        match compressed {
+            Some(compressed) => {
+                let mut single = zip::ZipArchive::new(io::Cursor::new(compressed.zip))?;
+                zip.raw_copy_file(single.by_index(0)?)?;
+                listed.push(compressed.listed);
+            }
+            None => listed.push(write_entry(
+                &mut zip,
+                source,
+                name,
+                transforms,
+                *file_options,
+            )?),
+        }
+    }
+
+    if verbose {
+        eprintln!("  adding manifest.xml");
+    }
+    zip.start_file("manifest.xml", options)?;
+    let mut entry = Hashing::new(&mut zip);
+    io::Write::write_all(&mut entry, manifest)?;
+    listed.push(entry.listed("manifest.xml".to_string()));
+
+    if let Some(name) = &settings.build_constants {
+        add_parent_dirs(&mut zip, &mut dirs, name, options, verbose)?;
+        if verbose {
+            eprintln!("  adding {name}");
+        }
+        zip.start_file(name.as_str(), options)?;
+        let mut entry = Hashing::new(&mut zip);
+        io::Write::write_all(&mut entry, build_constants(root, manifest)?.as_bytes())?;
+        listed.push(entry.listed(name.clone()));
+    }
+
+    if settings.file_list != Some(false) {
+        if verbose {
+            eprintln!("  adding {FILE_LIST}");
+        }
+        zip.start_file(FILE_LIST, options)?;
+        let list = serde_json::to_vec_pretty(&serde_json::json!({ "files": listed }))?;
+        io::Write::write_all(&mut zip, &list)?;
+    }
+
+    zip.set_comment(build_comment(root, manifest)?);
+    restore_file_types(&mut zip.finish()?)?;
+    Ok(listed)
+}
+
+/// Adds `source` as `name`. Untransformed files go straight from disk into the
+/// archive, hashed on the way.
+fn write_entry<W: io::Write + Seek>(
+    zip: &mut zip::ZipWriter<W>,
+    source: &SourceFile,
+    name: &str,
+    transforms: &Pipeline,
+    options: FileOptions,
+) -> Result<ListedFile, Box<dyn std::error::Error>> {
+    let mode = file_mode(&source.path)?;
+    let transformed = if transforms.applies_to(source) {
+        Some(transforms.apply(source, fs::read(&source.path)?)?)
+    } else {
+        None
+    };
+    let size = match &transformed {
+        Some(contents) => contents.len() as u64,
+        None => fs::metadata(&source.path)?.len(),
+    };
+    let options = options.unix_permissions(mode).large_file(needs_zip64(size));
+    zip.start_file(name, options)?;
+    let mut entry = Hashing::new(zip);
+    match transformed {
+        Some(contents) => io::Write::write_all(&mut entry, &contents)?,
+        None => {
+            io::copy(&mut File::open(&source.path)?, &mut entry)?;
+        }
+    }
+    Ok(entry.listed(name.to_string()))
+}
+
+/// An entry compressed into an archive of its own, copied over as is.
+struct Compressed {
+    zip: Vec<u8>,
+    listed: ListedFile,
+}
+
+/// Files up to this size are compressed in parallel and held in memory until
+/// their turn, bigger ones are streamed into the archive one after another.
+const PARALLEL_LIMIT: u64 = 16 * 1024 * 1024;
+
+/// Compresses the `jobs` of at most [`PARALLEL_LIMIT`] bytes on every core,
+/// `None` for the others. The entries come out exactly as [`write_entry`]
+/// would write them, so the archive doesn't depend on the number of threads.
+fn compress_small(
+    jobs: &[(&SourceFile, String, FileOptions)],
+    transforms: &Pipeline,
+) -> Result<Vec<Option<Compressed>>, Box<dyn std::error::Error>> {
+    let mut small = Vec::new();
+    for (i, (source, _, _)) in jobs.iter().enumerate() {
+        if fs::metadata(&source.path)?.len() <= PARALLEL_LIMIT {
+            small.push(i);
+        }
+    }
+    let threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(small.len());
+    let next = AtomicUsize::new(0);
+    let done: Vec<(usize, Result<Compressed, String>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    while let Some(&i) = small.get(next.fetch_add(1, atomic::Ordering::Relaxed)) {
+                        let (source, name, options) = &jobs[i];
+                        done.push((i, compress(source, name, transforms, *options)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("compression worker panicked"))
+            .collect()
+    });
+
+    let mut compressed: Vec<Option<Compressed>> = jobs.iter().map(|_| None).collect();
+    for (i, result) in done {
+        compressed[i] = Some(result?);
+    }
+    Ok(compressed)
+}
+
+fn compress(
+    source: &SourceFile,
+    name: &str,
+    transforms: &Pipeline,
+    options: FileOptions,
+) -> Result<Compressed, String> {
+    let mut zip = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    let listed =
+        write_entry(&mut zip, source, name, transforms, options).map_err(|e| e.to_string())?;
+    let zip = zip.finish().map_err(|e| e.to_string())?.into_inner();
+    Ok(Compressed { zip, listed })
+}
+
+/// zip 0.6 keeps only the permission bits of entries added with `raw_copy_file`,
+/// this puts back the regular file type [`write_entry`] gives them in the
+/// central directory, to end up with the same archive.
+fn restore_file_types(file: &mut File) -> Result<(), Box<dyn std::error::Error>> {
+    file.rewind()?;
+    let mut zip = zip::ZipArchive::new(&mut *file)?;
+    let mut untyped = Vec::new();
+    for i in 0..zip.len() {
+        let entry = zip.by_index_raw(i)?;
+        if let Some(mode) = entry.unix_mode()
+            && mode & 0o170000 == 0
+        {
+            untyped.push((entry.central_header_start(), mode as u16));
+        }
+    }
+    drop(zip);
+    for (header, mode) in untyped {
+        // the mode is the upper half of the external attributes, 38 bytes in;
+        // make sure that is what sits there before overwriting it
+        let mut fields = [0; 42];
+        file.seek(io::SeekFrom::Start(header))?;
+        io::Read::read_exact(file, &mut fields)?;
+        if fields[..4] != *b"PK\x01\x02" || fields[40..] != mode.to_le_bytes() {
+            return Err(format!(
+                "no central directory header with mode {mode:o} at offset {header}"
+            )
+            .into());
+        }
+        file.seek(io::SeekFrom::Start(header + 40))?;
+        io::Write::write_all(file, &(mode | 0o100000).to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Archive comment naming the tool, its version, when and from which commit it
+/// was built. The time is `SOURCE_DATE_EPOCH` or else that of the commit, and
+/// left out when there is neither, so rebuilding the same sources gives the
+/// same archive.
+fn build_comment(root: &Path, manifest: &[u8]) -> Result<String, String> {
+    let (contents, _) = decode(manifest)?;
+    let (id, version) = parse_manifest(&contents).map_err(|e| e.to_string())?;
+    let commit = git(root, &["rev-parse", "HEAD"]).ok();
+    let seconds = match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch.trim().parse().ok(),
+        Err(_) => git(root, &["log", "-1", "--format=%ct"])
+            .ok()
+            .and_then(|seconds| seconds.parse().ok()),
+    };
+    let built = seconds.and_then(|seconds| time::OffsetDateTime::from_unix_timestamp(seconds).ok());
+    let mut comment = format!("Id: {id}\nVersion: {version}\n");
+    if let Some(built) = built {
+        comment.push_str(&format!(
+            "Built: {:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z\n",
+            built.year(),
+            u8::from(built.month()),
+            built.day(),
+            built.hour(),
+            built.minute(),
+            built.second()
+        ));
+    }
+    if let Some(commit) = commit {
+        comment.push_str(&format!("Commit: {commit}\n"));
+    }
+    Ok(comment)
+}
+
+/// Contents of the [`Settings::build_constants`] module for the tool described
+/// by `manifest`, the channel taken from its pre-release version.
+fn build_constants(root: &Path, manifest: &[u8]) -> Result<String, String> {
+    let (contents, _) = decode(manifest)?;
+    let (_, version) = parse_manifest(&contents).map_err(|e| e.to_string())?;
+    let pre = parse_version(&version).map(|v| v.pre).unwrap_or_default();
+    Ok(BuildInfo::current(root, &version).lua_module(split_pre(&pre).0))
+}
+
+/// Reads `archive` back and checks that every entry decompresses with a valid
+/// checksum, that the `written` files have the expected contents and that
+/// manifest.xml (and `main.lua`, if set) sit at the top level.
+pub fn verify(
+    archive: &Path,
+    written: &[ListedFile],
+    main_lua: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let failed = |problem: String| format!("verifying {} failed: {problem}", archive.display());
+    let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+    let hashes = entry_hashes(&mut zip).map_err(|e| failed(e.to_string()))?;
+    for file in written {
+        match hashes.get(&file.name) {
+            None => return Err(failed(format!("{} is missing", file.name)).into()),
+            Some(hash) if *hash != file.sha256 => {
+                return Err(
+                    failed(format!("{} does not match what was written", file.name)).into(),
+                );
+            }
+            Some(_) => {}
+        }
+    }
+    if !hashes.contains_key("manifest.xml") {
+        return Err(failed("no top-level manifest.xml".to_string()).into());
+    }
+    if main_lua && !hashes.contains_key("main.lua") {
+        return Err(failed("no top-level main.lua".to_string()).into());
+    }
+    Ok(())
+}
+
+/// Whether an entry of `size` bytes may not fit the 4 GiB of a plain zip entry,
+/// leaving room for deflate making incompressible data slightly bigger. The
+/// zip64 records for more than 65535 entries are written automatically.
+fn needs_zip64(size: u64) -> bool {
+    size > u64::from(u32::MAX) - u64::from(u32::MAX) / 64
+}
+
+/// Timestamp for every entry: `SOURCE_DATE_EPOCH` if set, as in other
+/// reproducible builds, else the earliest date a zip can store.
+fn source_date() -> Result<zip::DateTime, String> {
+    let Ok(epoch) = std::env::var("SOURCE_DATE_EPOCH") else {
+        return Ok(zip::DateTime::default());
+    };
+    let invalid =
+        || format!("SOURCE_DATE_EPOCH '{epoch}' is not a timestamp zip archives can store");
+    let seconds: i64 = epoch.trim().parse().map_err(|_| invalid())?;
+    let at = time::OffsetDateTime::from_unix_timestamp(seconds).map_err(|_| invalid())?;
+    zip::DateTime::try_from(at).map_err(|_| invalid())
+}
+
+/// Modification time of `path` for `keep_mtimes`, the earliest date a zip can
+/// store if it doesn't fit.
+fn modified_time(path: &Path) -> io::Result<zip::DateTime> {
+    let modified = time::OffsetDateTime::from(fs::metadata(path)?.modified()?);
+    Ok(zip::DateTime::try_from(modified).unwrap_or_default())
+}
+
+/// Entry names for `sources` in Unicode NFC, so the archive extracts the same on
+/// macOS and Windows. Fails on two files packaged under the same name, on names
+/// that only differ in normalization or case, as one of them would be lost on
+/// those systems, and on characters or names some systems can't store.
+fn entry_names(sources: &[&SourceFile]) -> Result<Vec<String>, String> {
+    let mut seen: HashMap<String, &SourceFile> = HashMap::new();
+    let mut folded: HashMap<String, &str> = HashMap::new();
+    let mut names = Vec::new();
+    for source in sources {
+        check_entry_name(&source.name)?;
+        let name: String = source.name.nfc().collect();
+        if let Some(other) = seen.insert(name.clone(), source) {
+            if other.name == source.name {
+                return Err(format!(
+                    "{} and {} would both be packaged as '{}', exclude one of them",
+                    other.path.display(),
+                    source.path.display(),
+                    source.name
+                ));
+            }
+            return Err(format!(
+                "'{}' and '{}' only differ in Unicode normalization, rename one of them",
+                other.name, source.name
+            ));
+        }
+        if let Some(other) = folded.insert(name.to_lowercase(), &source.name) {
+            return Err(format!(
+                "'{other}' and '{}' only differ in case and would overwrite each other \
+                 on Windows and macOS, rename one of them",
+                source.name
+            ));
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+/// Rejects path components Windows can't create or other systems treat
+/// inconsistently: control and reserved characters, a trailing dot or space,
+/// and device names like `CON` or `com1.lua`.
+fn check_entry_name(name: &str) -> Result<(), String> {
+    const RESERVED: &[&str] = &["CON", "PRN", "AUX", "NUL"];
+    for component in name.split('/') {
+        let problem = if let Some(c) = component
+            .chars()
+            .find(|c| c.is_control() || "<>:\"\\|?*".contains(*c))
+        {
+            format!("contains {c:?}")
+        } else if component.ends_with(['.', ' ']) {
+            "ends with a dot or space".to_string()
+        } else {
+            let stem = component
+                .split('.')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_uppercase();
+            let device = RESERVED.contains(&stem.as_str())
+                || (stem.len() == 4
+                    && (stem.starts_with("COM") || stem.starts_with("LPT"))
+                    && stem.ends_with(|c: char| c.is_ascii_digit()));
+            if !device {
+                continue;
+            }
+            format!("uses the reserved device name {stem}")
+        };
+        return Err(format!("cannot package '{name}': '{component}' {problem}"));
+    }
+    Ok(())
+}
+
+/// Adds an entry for each folder above `name` not in `dirs` yet, so extracted
+/// folders get 0o755 rather than whatever the unzipping tool picks.
+fn add_parent_dirs<W: io::Write + Seek>(
+    zip: &mut zip::ZipWriter<W>,
+    dirs: &mut BTreeSet<String>,
+    name: &str,
+    options: FileOptions,
+    verbose: bool,
+) -> zip::result::ZipResult<()> {
+    for (end, _) in name.match_indices('/') {
+        let dir = &name[..=end];
+        if dirs.insert(dir.to_string()) {
+            if verbose {
+                eprintln!("  adding {dir}");
+            }
+            zip.add_directory(dir, options.unix_permissions(0o755))?;
+        }
+    }
+    Ok(())
+}
+
+/// Permission bits for `path`: 0o755 if it is executable at all, e.g. to keep
+/// bundled helper binaries runnable, 0o644 otherwise, so the umask of the
+/// checkout doesn't change the archive.
+#[cfg(unix)]
+fn file_mode(path: &Path) -> io::Result<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(normalized_mode(fs::metadata(path)?.permissions().mode()))
+}
+
+#[cfg(unix)]
+fn normalized_mode(mode: u32) -> u32 {
+    if mode & 0o111 != 0 { 0o755 } else { 0o644 }
+}
+
+#[cfg(not(unix))]
+fn file_mode(_path: &Path) -> io::Result<u32> {
+    Ok(0o644)
+}
+
+/// Passes writes on to `inner`, keeping their size and SHA-256 for [`FILE_LIST`].
+struct Hashing<W> {
+    inner: W,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<W: io::Write> Hashing<W> {
+    fn new(inner: W) -> Self {
+        Hashing {
+            inner,
+            hasher: Sha256::new(),
+            size: 0,
+        }
+    }
+
+    fn listed(self, name: String) -> ListedFile {
+        ListedFile {
+            name,
+            size: self.size,
+            sha256: format!("{:x}", self.hasher.finalize()),
+        }
+    }
+}
+
+impl<W: io::Write> io::Write for Hashing<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Extracts `archive` into `dest`, refusing entries that would land outside of it
+/// and archives that are not a tool (no top-level `manifest.xml`).
+pub fn extract(
+    archive: &Path,
+    dest: &Path,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+
+    if zip.by_name("manifest.xml").is_err() {
+        return Err(format!("{} contains no manifest.xml", archive.display()).into());
+    }
+    for i in 0..zip.len() {
+        let entry = zip.by_index(i)?;
+        if entry.enclosed_name().is_none() {
+            return Err(format!("refusing to extract unsafe path '{}'", entry.name()).into());
+        }
+    }
+
+    fs::create_dir_all(dest)?;
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        let Some(relative) = entry.enclosed_name().map(|p| p.to_owned()) else {
+            continue;
+        };
+        let path = dest.join(relative);
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if verbose {
+            println!("  extracting {}", entry.name());
+        }
+        let mut f = File::create(&path)?;
+        io::copy(&mut entry, &mut f)?;
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode()
+            && mode & 0o111 != 0
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode & 0o755))?;
+        }
+    }
+    Ok(())
+}
+
+/// Contents of the archive's top-level `manifest.xml`.
+pub fn archive_manifest<R: Read + Seek>(
+    zip: &mut zip::ZipArchive<R>,
+    archive: &Path,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut contents = String::new();
+    zip.by_name("manifest.xml")
+        .map_err(|_| format!("{} contains no manifest.xml", archive.display()))?
+        .read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+/// SHA-256 of every file entry in the archive, keyed by entry name.
+pub fn entry_hashes<R: Read + Seek>(
+    zip: &mut zip::ZipArchive<R>,
+) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+    let mut hashes = BTreeMap::new();
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let mut hasher = Sha256::new();
+        io::copy(&mut entry, &mut hasher)?;
+        hashes.insert(entry.name().to_string(), format!("{:x}", hasher.finalize()));
+    }
+    Ok(hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Assets, LineEnding, Transforms};
+
+    const MANIFEST: &[u8] = b"<?xml version=\"1.0\"?>\n<RenoiseScriptingTool doc_version=\"0\">\n  <ApiVersion>6</ApiVersion>\n  <Id>com.example.Tool</Id>\n  <Name>Tool</Name>\n  <Version>1.0</Version>\n</RenoiseScriptingTool>\n";
+
+    /// An empty project folder of its own for the test called `name`.
+    fn project(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rnplug-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("main.lua"), "print('hi')\n").unwrap();
+        dir
+    }
+
+    /// Packages `root` into `release/com.example.Tool.xrnx`, as `package` does.
+    fn package(root: &Path, settings: &Settings) -> PathBuf {
+        let out = settings.release_dir(root).join("com.example.Tool.xrnx");
+        fs::create_dir_all(out.parent().unwrap()).unwrap();
+        let overrides = BTreeMap::new();
+        let transforms = Pipeline::for_build(root, settings, &overrides, true);
+        let staged = out.with_file_name(format!("{TEMP_PREFIX}com.example.Tool.xrnx"));
+        zip_sources(root, settings, MANIFEST, &transforms, &[], &staged, false).unwrap();
+        fs::rename(&staged, &out).unwrap();
+        out
+    }
+
+    fn entries(archive: &Path) -> BTreeMap<String, Vec<u8>> {
+        let mut zip = zip::ZipArchive::new(File::open(archive).unwrap()).unwrap();
+        let mut entries = BTreeMap::new();
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i).unwrap();
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents).unwrap();
+            entries.insert(entry.name().to_string(), contents);
+        }
+        entries
+    }
+
+    #[test]
+    fn packages_binary_assets_unchanged() {
+        let root = project("assets");
+        let png: Vec<u8> = (0..=255).chain([b'\r', b'\n', 0, b'\n']).collect();
+        let wav: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+        fs::write(root.join("icon.png"), &png).unwrap();
+        fs::create_dir_all(root.join("samples")).unwrap();
+        fs::write(root.join("samples/kick.wav"), &wav).unwrap();
+        fs::write(root.join("notes.bin"), b"not an asset").unwrap();
+        let settings = Settings {
+            assets: Some(Assets {
+                extensions: vec!["PNG".to_string()],
+                dirs: vec![PathBuf::from("samples")],
+            }),
+            transforms: Some(Transforms {
+                line_endings: Some(LineEnding::Crlf),
+                ..Transforms::default()
+            }),
+            ..Settings::default()
+        };
+
+        let entries = entries(&package(&root, &settings));
+        assert_eq!(entries["icon.png"], png);
+        assert_eq!(entries["samples/kick.wav"], wav);
+        assert_eq!(entries["main.lua"], b"print('hi')\r\n");
+        assert!(!entries.contains_key("notes.bin"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn selects_assets_by_extension_and_folder() {
+        let root = project("select-assets");
+        for file in [
+            "icons/logo.PNG",
+            "icons/logo.svg",
+            "samples/kick.wav",
+            "samples/deep/readme.txt",
+            "samples/skip.wav",
+            "samples/.hidden.wav",
+            "docs/notes.txt",
+            "bin/helper",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, file).unwrap();
+        }
+        fs::write(root.join(IGNORE_FILE), "samples/skip.wav\n").unwrap();
+        let settings = Settings {
+            assets: Some(Assets {
+                extensions: vec!["png".to_string(), ".svg".to_string()],
+                dirs: vec![PathBuf::from("samples"), PathBuf::from("bin")],
+            }),
+            ..Settings::default()
+        };
+
+        let names: Vec<String> = collect_sources(&root, &settings, false)
+            .unwrap()
+            .into_iter()
+            .map(|source| source.name)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "bin/helper",
+                "icons/logo.PNG",
+                "icons/logo.svg",
+                "main.lua",
+                "samples/deep/readme.txt",
+                "samples/kick.wav",
+            ]
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_assets_executable() {
+        use std::os::unix::fs::PermissionsExt;
+        let root = project("exec-assets");
+        fs::create_dir_all(root.join("bin")).unwrap();
+        fs::write(root.join("bin/helper"), b"\x7fELF").unwrap();
+        fs::set_permissions(root.join("bin/helper"), fs::Permissions::from_mode(0o775)).unwrap();
+        fs::write(root.join("bin/data"), b"data").unwrap();
+        fs::set_permissions(root.join("bin/data"), fs::Permissions::from_mode(0o664)).unwrap();
+        let settings = Settings {
+            assets: Some(Assets {
+                dirs: vec![PathBuf::from("bin")],
+                ..Assets::default()
+            }),
+            ..Settings::default()
+        };
+
+        let archive = package(&root, &settings);
+        let mut zip = zip::ZipArchive::new(File::open(&archive).unwrap()).unwrap();
+        let mode = |zip: &mut zip::ZipArchive<File>, name: &str| {
+            zip.by_name(name).unwrap().unix_mode().unwrap()
+        };
+        assert_eq!(mode(&mut zip, "bin/helper"), 0o100755);
+        assert_eq!(mode(&mut zip, "bin/data"), 0o100644);
+        assert_eq!(mode(&mut zip, "bin/"), 0o40755);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn leaves_earlier_builds_out() {
+        let root = project("nested");
+        fs::create_dir_all(root.join("assets")).unwrap();
+        fs::write(root.join("assets/logo.xrnx"), "bundled").unwrap();
+        fs::write(root.join(format!("{TEMP_PREFIX}old.xrnx")), "").unwrap();
+        let settings = Settings {
+            assets: Some(Assets {
+                extensions: vec!["xrnx".to_string(), "zip".to_string()],
+                dirs: vec![PathBuf::from("release")],
+            }),
+            // spelled differently from the folder the walk comes across
+            output: Some(PathBuf::from("./release/../release")),
+            ..Settings::default()
+        };
+
+        let first = entries(&package(&root, &settings));
+        fs::write(settings.release_dir(&root).join("com.example.Tool.zip"), "").unwrap();
+        let second = entries(&package(&root, &settings));
+        assert_eq!(
+            first.keys().collect::<Vec<_>>(),
+            second.keys().collect::<Vec<_>>()
+        );
+        assert!(second.contains_key("assets/logo.xrnx"));
+        assert!(
+            !second
+                .keys()
+                .any(|name| name.starts_with("release/") || name.contains(TEMP_PREFIX)),
+            "{:?}",
+            second.keys()
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn tells_duplicates_from_normalization() {
+        let source = |name: &str, path: &str| SourceFile {
+            name: name.to_string(),
+            path: PathBuf::from(path),
+        };
+        let (a, b) = (
+            source("lib/a.lua", "lib/a.lua"),
+            source("lib/a.lua", "src/a.lua"),
+        );
+        let error = entry_names(&[&a, &b]).unwrap_err();
+        assert!(error.contains("both be packaged as 'lib/a.lua'"), "{error}");
+
+        let (nfc, nfd) = (source("caf\u{e9}.lua", "1"), source("cafe\u{301}.lua", "2"));
+        let error = entry_names(&[&nfc, &nfd]).unwrap_err();
+        assert!(error.contains("Unicode normalization"), "{error}");
+    }
+
+    #[test]
+    fn packages_a_file_given_twice_once() {
+        let root = project("twice");
+        let extra = [SourceFile {
+            name: "main.lua".to_string(),
+            path: root.join("main.lua"),
+        }];
+        let out = root.join(format!("{TEMP_PREFIX}twice.xrnx"));
+        let settings = Settings::default();
+        let overrides = BTreeMap::new();
+        let transforms = Pipeline::for_build(&root, &settings, &overrides, true);
+        zip_sources(&root, &settings, MANIFEST, &transforms, &extra, &out, false).unwrap();
+        let names: Vec<_> = entries(&out).into_keys().collect();
+        assert_eq!(names, ["files.json", "main.lua", "manifest.xml"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn normalizes_file_modes() {
+        for (mode, normalized) in [
+            (0o644, 0o644),
+            (0o664, 0o644),
+            (0o600, 0o644),
+            (0o666, 0o644),
+            (0o755, 0o755),
+            (0o775, 0o755),
+            (0o700, 0o755),
+            (0o100744, 0o755),
+        ] {
+            assert_eq!(normalized_mode(mode), normalized, "{mode:o}");
+        }
+    }
+
+    #[test]
+    fn needs_zip64_near_4_gib() {
+        assert!(!needs_zip64(0));
+        assert!(!needs_zip64(u64::from(u32::MAX) / 2));
+        assert!(needs_zip64(u64::from(u32::MAX) - u64::from(u32::MAX) / 128));
+        assert!(needs_zip64(u64::from(u32::MAX) + 1));
+    }
+
+    #[test]
+    fn writes_zip64_for_more_than_65535_entries() {
+        let root = project("zip64-entries");
+        let lib = root.join("lib");
+        fs::create_dir_all(&lib).unwrap();
+        for i in 0..70_000 {
+            fs::write(lib.join(format!("m{i}.lua")), i.to_string()).unwrap();
+        }
+        let settings = Settings {
+            fast: Some(true),
+            ..Settings::default()
+        };
+        let archive = package(&root, &settings);
+
+        let mut zip = zip::ZipArchive::new(File::open(&archive).unwrap()).unwrap();
+        // the files, lib/, main.lua, manifest.xml and files.json
+        assert_eq!(zip.len(), 70_004);
+        let mut entry = zip.by_name("lib/m69999.lua").unwrap();
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "69999");
+        drop(entry);
+        // the small files were compressed apart and copied in, which loses their type
+        for i in 0..zip.len() {
+            let entry = zip.by_index_raw(i).unwrap();
+            let file_type = if entry.is_dir() { 0o040000 } else { 0o100000 };
+            assert_eq!(
+                entry.unix_mode().unwrap() & 0o170000,
+                file_type,
+                "{}",
+                entry.name()
+            );
+        }
+        assert!(has_zip64_end_record(&archive));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Whether the archive ends with a zip64 end of central directory record
+    /// and its locator.
+    fn has_zip64_end_record(archive: &Path) -> bool {
+        let bytes = fs::read(archive).unwrap();
+        let tail = &bytes[bytes.len().saturating_sub(1024)..];
+        let find = |signature: &[u8]| tail.windows(4).any(|w| w == signature);
+        find(b"PK\x06\x06") && find(b"PK\x06\x07")
+    }
+
+    #[test]
+    fn writes_small_archives_without_zip64() {
+        let root = project("zip64-small");
+        let archive = package(&root, &Settings::default());
+        assert!(!has_zip64_end_record(&archive));
+        let entries = entries(&archive);
+        assert_eq!(entries["main.lua"], b"print('hi')\n");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Writes and reads back an entry over 4 GiB, which takes a while.
+    #[test]
+    #[ignore]
+    fn writes_zip64_for_entries_over_4_gib() {
+        let root = project("zip64-size");
+        let size = u64::from(u32::MAX) + 4096;
+        File::create(root.join("big.lua"))
+            .unwrap()
+            .set_len(size)
+            .unwrap();
+        let settings = Settings {
+            fast: Some(true),
+            file_list: Some(false),
+            ..Settings::default()
+        };
+        let archive = package(&root, &settings);
+
+        let mut zip = zip::ZipArchive::new(File::open(&archive).unwrap()).unwrap();
+        let mut entry = zip.by_name("big.lua").unwrap();
+        assert_eq!(entry.size(), size);
+        assert_eq!(io::copy(&mut entry, &mut io::sink()).unwrap(), size);
+        drop(entry);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,484 @@
+use crate::cli::{BuildMeta, BumpArgs, GlobalArgs, VersionSource};
+use crate::config::{Settings, VersionFormat, VersionScheme, VersionStrategy};
+use crate::conventional::infer_bump;
+use crate::git::{self, git};
+use crate::manifest::{
+    Encoding, ensure_doc_version, parse_manifest, read_file, render, set_version, write_file,
+};
+use crate::sync::{SyncedFile, sync_versions};
+use crate::version::{
+    BumpKind, ToolVersion, bump, bump_calver, bump_keep_lower, bump_pre, promote,
+};
+use semver::BuildMetadata;
+use std::fs;
+use std::path::Path;
+use time::OffsetDateTime;
+
+pub struct Bumped {
+    pub tool_id: String,
+    pub old_version: String,
+    pub new_version: String,
+    /// Manifest file contents before the bump, `None` if there was no file yet.
+    pub original: Option<String>,
+    /// Manifest contents with the new version.
+    pub updated: String,
+    /// Other files carrying the version, see `version_sync` in rnplug.toml.
+    pub synced: Vec<SyncedFile>,
+    /// How the manifest file is encoded, it is written back the same way.
+    pub encoding: Encoding,
+}
+
+/// Reads the manifest, bumps its version and writes it back in place (unless
+/// `--plan` only asks what the new version would be).
+pub fn bump_manifest(
+    global: &GlobalArgs,
+    settings: &Settings,
+    args: &BumpArgs,
+) -> Result<Bumped, Box<dyn std::error::Error>> {
+    let bumped = plan_bump(global, settings, args)?;
+    if !args.plan {
+        bumped.write(global)?;
+    }
+    Ok(bumped)
+}
+
+impl Bumped {
+    /// Writes the new manifest and synced files.
+    pub fn write(&self, global: &GlobalArgs) -> Result<(), Box<dyn std::error::Error>> {
+        write_file(&global.manifest, &self.updated, self.encoding)?;
+        for file in &self.synced {
+            fs::write(global.project_root().join(&file.path), &file.updated)?;
+        }
+        Ok(())
+    }
+
+    /// Puts back what was there before [`Bumped::write`].
+    pub fn restore(&self, global: &GlobalArgs) -> Result<(), Box<dyn std::error::Error>> {
+        match &self.original {
+            Some(original) => write_file(&global.manifest, original, self.encoding)?,
+            None => fs::remove_file(&global.manifest)?,
+        }
+        for file in &self.synced {
+            fs::write(global.project_root().join(&file.path), &file.original)?;
+        }
+        Ok(())
+    }
+}
+
+/// Works out the bumped manifest without writing it.
+pub fn plan_bump(
+    global: &GlobalArgs,
+    settings: &Settings,
+    args: &BumpArgs,
+) -> Result<Bumped, Box<dyn std::error::Error>> {
+    let (original, manifest_str, encoding) = rendered_manifest(global, settings)?;
+    let (tool_id, old_version) = parse_manifest(&manifest_str)
+        .map_err(|e| format!("failed to parse {}: {e}", global.manifest.display()))?;
+
+    let current = ToolVersion::parse(&old_version)
+        .map_err(|e| format!("invalid version '{old_version}': {e}"))?;
+    let strategy = if args.selects_version() {
+        None
+    } else {
+        settings.bump
+    };
+    let default_kind = default_kind(strategy);
+
+    let kind = if args.conventional || strategy == Some(VersionStrategy::ConventionalCommits) {
+        let root = global.project_root();
+        let tag = git::last_tag(root, "v*");
+        let messages = git::commit_messages(root, tag.as_deref())?;
+        let since = tag.as_deref().unwrap_or("the first commit");
+        let kind = infer_bump(messages.iter().map(String::as_str)).ok_or_else(|| {
+            format!("no feat, fix or breaking commits since {since}, nothing to release")
+        })?;
+        if global.verbose {
+            eprintln!(
+                "{} commit(s) since {since} call for a {kind:?} bump",
+                messages.len()
+            );
+        }
+        Some(kind)
+    } else {
+        args.explicit_kind()
+    };
+    let from_tag = matches!(args.version_from, Some(VersionSource::GitTag))
+        || strategy == Some(VersionStrategy::GitTag);
+    if args.commit_count && !from_tag {
+        return Err(
+            "--commit-count needs the version from a tag, pass --version-from git-tag or set bump = \"git-tag\""
+                .into(),
+        );
+    }
+    let version = if from_tag {
+        tag_version(global.project_root(), args.commit_count)?
+    } else if strategy == Some(VersionStrategy::Manual) {
+        let mut version = current.clone();
+        version.version.build = BuildMetadata::EMPTY;
+        version
+    } else if args.promote {
+        let mut next = current.clone();
+        next.version.build = BuildMetadata::EMPTY;
+        promote(&mut next.version, &settings.channels())?;
+        next
+    } else if settings.scheme == Some(VersionScheme::Calver) && !args.selects_version() {
+        if args.pre.is_some() {
+            return Err("--pre is not supported with the calver scheme".into());
+        }
+        let mut next = current.clone();
+        next.version.build = BuildMetadata::EMPTY;
+        bump_calver(&mut next.version, OffsetDateTime::now_utc().date());
+        next.components = 3;
+        next
+    } else {
+        let keep_lower = args.keep_patch || settings.keep_patch == Some(true);
+        next_version(&current, args, kind, default_kind, keep_lower)?
+    };
+    finish(
+        global,
+        settings,
+        args,
+        tool_id,
+        old_version,
+        (original, manifest_str, encoding),
+        version,
+    )
+}
+
+/// Plans the next build on the `channel` pre-release stream. It continues from
+/// `last`, the channel's previous version, unless the manifest (the stable line)
+/// has moved past it, in which case a new pre-release of the next version starts.
+pub fn plan_channel_bump(
+    global: &GlobalArgs,
+    settings: &Settings,
+    args: &BumpArgs,
+    channel: &str,
+    last: Option<&str>,
+) -> Result<Bumped, Box<dyn std::error::Error>> {
+    let (original, manifest_str, encoding) = rendered_manifest(global, settings)?;
+    let (tool_id, old_version) = parse_manifest(&manifest_str)
+        .map_err(|e| format!("failed to parse {}: {e}", global.manifest.display()))?;
+
+    let mut base = ToolVersion::parse(&old_version)
+        .map_err(|e| format!("invalid version '{old_version}': {e}"))?;
+    if let Some(last) = last {
+        let last = ToolVersion::parse(last)
+            .map_err(|e| format!("invalid {channel} version '{last}': {e}"))?;
+        if last.version.cmp_precedence(&base.version) == std::cmp::Ordering::Greater {
+            base = last;
+        }
+    }
+    base.version.build = BuildMetadata::EMPTY;
+    let mut version = base;
+    bump_pre(
+        &mut version.version,
+        channel,
+        args.explicit_kind(),
+        default_kind(settings.bump),
+    )
+    .map_err(|e| format!("invalid channel '{channel}': {e}"))?;
+
+    finish(
+        global,
+        settings,
+        args,
+        tool_id,
+        old_version,
+        (original, manifest_str, encoding),
+        version,
+    )
+}
+
+/// The manifest as it gets packaged: manifest.xml with the generated fields (see
+/// [`Settings::generated_fields`]) and a missing `doc_version` filled in, made
+/// up from the fields alone if there is no file. Also returns the file as it is,
+/// nothing is written.
+pub fn rendered_manifest(
+    global: &GlobalArgs,
+    settings: &Settings,
+) -> Result<(Option<String>, String, Encoding), Box<dyn std::error::Error>> {
+    let path = &global.manifest;
+    let fields = settings.generated_fields(global.project_root())?;
+    let (current, encoding) = if path.exists() {
+        let (contents, encoding) =
+            read_file(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        (Some(contents), encoding)
+    } else if fields.is_empty() {
+        return Err(format!("{} not found", path.display()).into());
+    } else {
+        (None, Encoding::default())
+    };
+    let rendered = ensure_doc_version(&render(current.as_deref(), &fields)?)?;
+    Ok((current, rendered, encoding))
+}
+
+fn default_kind(strategy: Option<VersionStrategy>) -> BumpKind {
+    match strategy {
+        Some(VersionStrategy::Major) => BumpKind::Major,
+        Some(VersionStrategy::Patch) => BumpKind::Patch,
+        _ => BumpKind::Minor,
+    }
+}
+
+/// Applies build metadata and formatting to the chosen `version` and renders
+/// the manifest and synced files with it.
+fn finish(
+    global: &GlobalArgs,
+    settings: &Settings,
+    args: &BumpArgs,
+    tool_id: String,
+    old_version: String,
+    (original, manifest_str, encoding): (Option<String>, String, Encoding),
+    mut version: ToolVersion,
+) -> Result<Bumped, Box<dyn std::error::Error>> {
+    if let Some(BuildMeta::Git) = args.build_meta {
+        let stamp = git_build_metadata(global.project_root())?;
+        let build = &mut version.version.build;
+        *build = if build.is_empty() {
+            stamp
+        } else {
+            BuildMetadata::new(&format!("{build}.{stamp}"))?
+        };
+    }
+    match settings.version_format {
+        Some(VersionFormat::Full) => version.components = 3,
+        Some(VersionFormat::Short) => version.components = 2,
+        Some(VersionFormat::Preserve) | None => {}
+    }
+    let new_version = version.to_string();
+    if global.verbose {
+        eprintln!("Version {old_version} -> {new_version}");
+    }
+
+    let updated = set_version(&manifest_str, &new_version)?;
+    let synced = sync_versions(global.project_root(), &settings.version_sync, &new_version)?;
+    if global.verbose {
+        for file in &synced {
+            eprintln!("Version synced into {}", file.path.display());
+        }
+    }
+    Ok(Bumped {
+        tool_id,
+        old_version,
+        new_version,
+        original,
+        updated,
+        synced,
+        encoding,
+    })
+}
+
+/// The version `args` asks for, starting from `current`, with `kind` overriding the
+/// bump kind chosen on the command line and `default` used when there is none.
+/// With `keep_lower` a plain bump leaves the lower components alone. Build metadata is never carried over, dev builds stamp their own.
+pub fn next_version(
+    current: &ToolVersion,
+    args: &BumpArgs,
+    kind: Option<BumpKind>,
+    default: BumpKind,
+    keep_lower: bool,
+) -> Result<ToolVersion, String> {
+    let mut current = current.clone();
+    current.version.build = BuildMetadata::EMPTY;
+
+    let Some(requested) = &args.set_version else {
+        let mut next = current.clone();
+        if args.no_bump {
+            return Ok(next);
+        }
+        match &args.pre {
+            Some(channel) => bump_pre(&mut next.version, channel, kind, default)
+                .map_err(|e| format!("invalid pre-release channel '{channel}': {e}"))?,
+            None if keep_lower => bump_keep_lower(&mut next.version, kind.unwrap_or(default)),
+            None => bump(&mut next.version, kind.unwrap_or(default)),
+        }
+        return Ok(next);
+    };
+
+    let version =
+        ToolVersion::parse(requested).map_err(|e| format!("invalid version '{requested}': {e}"))?;
+    // build metadata doesn't make a version newer
+    if version.version.cmp_precedence(&current.version) != std::cmp::Ordering::Greater
+        && !args.allow_downgrade
+    {
+        return Err(format!(
+            "{version} is not newer than the current version {current}, pass --allow-downgrade to use it anyway"
+        ));
+    }
+    Ok(version)
+}
+
+/// Version of the most recent `v*` tag, optionally with the number of commits
+/// since it as build metadata.
+fn tag_version(root: &Path, commit_count: bool) -> Result<ToolVersion, Box<dyn std::error::Error>> {
+    // `v1.4.0-3-gabc1234`: tag, commits since, abbreviated hash
+    let described = git(root, &["describe", "--tags", "--long", "--match", "v*"])
+        .map_err(|_| "no `v*` tag found to take the version from")?;
+    let mut parts = described.rsplitn(3, '-');
+    let (_, count, tag) = (parts.next(), parts.next(), parts.next());
+    let (Some(count), Some(tag)) = (count, tag) else {
+        return Err(format!("unexpected `git describe` output '{described}'").into());
+    };
+
+    let mut version = ToolVersion::parse(tag.trim_start_matches('v'))
+        .map_err(|e| format!("tag {tag} is not a version: {e}"))?;
+    if commit_count && count != "0" {
+        version.version.build = BuildMetadata::new(count)?;
+    }
+    Ok(version)
+}
+
+/// `<shortsha>.<yyyymmdd>` for the checked-out commit.
+fn git_build_metadata(root: &Path) -> Result<BuildMetadata, Box<dyn std::error::Error>> {
+    let sha = git(root, &["rev-parse", "--short", "HEAD"])?;
+    let today = OffsetDateTime::now_utc().date();
+    let stamp = format!(
+        "{sha}.{:04}{:02}{:02}",
+        today.year(),
+        u8::from(today.month()),
+        today.day()
+    );
+    Ok(BuildMetadata::new(&stamp)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_version(
+        current: &str,
+        requested: &str,
+        allow_downgrade: bool,
+    ) -> Result<String, String> {
+        let args = BumpArgs {
+            set_version: Some(requested.to_string()),
+            allow_downgrade,
+            ..BumpArgs::default()
+        };
+        let current = ToolVersion::parse(current).unwrap();
+        next_version(&current, &args, None, BumpKind::Minor, false).map(|v| v.to_string())
+    }
+
+    #[test]
+    fn set_version_needs_a_newer_version() {
+        for (current, requested, newer) in [
+            ("1.0.0", "1.0.1", true),
+            ("1.0.0", "1.0.0-rc.1", false),
+            ("1.0.0-rc.1", "1.0.0", true),
+            ("1.0.0", "1.0.0", false),
+            ("1.0.0", "1.0.0+build", false),
+            ("1.0.0+old", "1.0.0+new", false),
+            ("1.0.0+build", "1.0.0", false),
+            ("1.0.0", "0.9.0", false),
+        ] {
+            let result = set_version(current, requested, false);
+            assert_eq!(
+                result.is_ok(),
+                newer,
+                "{current} -> {requested}: {result:?}"
+            );
+            if !newer {
+                assert!(result.unwrap_err().contains("--allow-downgrade"));
+                assert_eq!(set_version(current, requested, true).unwrap(), requested);
+            }
+        }
+    }
+
+    #[test]
+    fn bump_needs_a_manifest_version() {
+        let dir = std::env::temp_dir().join(format!("rnplug-bump-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("manifest.xml");
+        fs::write(
+            &manifest,
+            "<RenoiseScriptingTool><Id>com.x.Tool</Id></RenoiseScriptingTool>",
+        )
+        .unwrap();
+        let global = GlobalArgs {
+            directory: None,
+            manifest,
+            verbose: false,
+            profile: None,
+        };
+        let error = plan_bump(&global, &Settings::default(), &BumpArgs::default())
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.starts_with("failed to parse"), "{error}");
+        assert!(error.contains("Version"), "{error}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn counts_commits_since_the_tag() {
+        let dir = std::env::temp_dir().join(format!("rnplug-bump-tag-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("manifest.xml");
+        fs::write(
+            &manifest,
+            "<RenoiseScriptingTool><Id>com.x.Tool</Id><Version>1.0</Version></RenoiseScriptingTool>",
+        )
+        .unwrap();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(["-c", "user.name=t", "-c", "user.email=t@x"])
+                .args(["-c", "commit.gpgSign=false"])
+                .args(args)
+                .current_dir(&dir)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?}");
+        };
+        git(&["init", "-q"]);
+        git(&["add", "."]);
+        git(&["commit", "-qm", "first"]);
+        git(&["tag", "v1.2.0"]);
+        for message in ["second", "third"] {
+            git(&["commit", "-q", "--allow-empty", "-m", message]);
+        }
+        let global = GlobalArgs {
+            directory: None,
+            manifest,
+            verbose: false,
+            profile: None,
+        };
+        let git_tag = Settings {
+            bump: Some(VersionStrategy::GitTag),
+            ..Settings::default()
+        };
+        let commit_count = BumpArgs {
+            commit_count: true,
+            ..BumpArgs::default()
+        };
+        let from_tag = BumpArgs {
+            version_from: Some(VersionSource::GitTag),
+            commit_count: true,
+            ..BumpArgs::default()
+        };
+        for (settings, args, expected) in [
+            (&git_tag, &commit_count, Ok("1.2.0+2")),
+            (&git_tag, &BumpArgs::default(), Ok("1.2.0")),
+            (&Settings::default(), &from_tag, Ok("1.2.0+2")),
+            (
+                &Settings::default(),
+                &commit_count,
+                Err(
+                    "--commit-count needs the version from a tag, pass --version-from git-tag or set bump = \"git-tag\"",
+                ),
+            ),
+        ] {
+            let result = plan_bump(&global, settings, args)
+                .map(|bumped| bumped.new_version)
+                .map_err(|e| e.to_string());
+            assert_eq!(
+                result,
+                expected.map(str::to_string).map_err(str::to_string),
+                "{args:?}"
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,274 @@
+use crate::lua::syntax_errors;
+use crate::placeholders::lua_string;
+use full_moon::ast::{Call, Expression, FunctionArgs, FunctionCall, Prefix, Suffix};
+use full_moon::tokenizer::{TokenReference, TokenType};
+use full_moon::visitors::Visitor;
+use full_moon::{LuaVersion, parse_fallible};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `main.lua` with the project modules it uses inlined.
+pub struct Bundle {
+    pub code: String,
+    /// The inlined module files, relative to the project root.
+    pub modules: Vec<PathBuf>,
+    /// Where the lines of each file, main.lua last, ended up in `code`.
+    pub sections: Vec<Section>,
+}
+
+/// Lines `line..line + lines` of the bundle are lines `1..=lines` of `source`.
+#[derive(Serialize)]
+pub struct Section {
+    /// Relative to the project root.
+    pub source: PathBuf,
+    pub line: usize,
+    pub lines: usize,
+}
+
+/// Inlines every project module `main_lua` requires, directly or through other
+/// modules. Each is registered in `package.preload`, so `require` still runs it
+/// once and keeps the result in `package.loaded`. Modules resolve against the
+/// directory of `main_lua`, which becomes the tool folder; requires of modules
+/// that aren't files there are left alone. Files in `overrides` (placeholders
+/// filled in, versions synced, keyed by their path relative to `root`) are
+/// bundled with those contents. With `source_map`, the bundle starts with
+/// [a helper](unbundle_helper) pointing errors at the original files.
+pub fn bundle(
+    root: &Path,
+    main_lua: &Path,
+    overrides: &BTreeMap<PathBuf, Vec<u8>>,
+    source_map: bool,
+) -> Result<Bundle, String> {
+    let read = |path: &Path| match path.strip_prefix(root).ok().and_then(|p| overrides.get(p)) {
+        Some(contents) => String::from_utf8(contents.clone())
+            .map_err(|_| format!("{} is not UTF-8", path.display())),
+        None => {
+            fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))
+        }
+    };
+    let main = read(main_lua)?;
+    let tool_dir = main_lua.parent().unwrap_or(root);
+    let mut pending = requires(&main, main_lua)?;
+    let mut modules: BTreeMap<String, (PathBuf, String)> = BTreeMap::new();
+    while let Some(name) = pending.pop() {
+        if modules.contains_key(&name) {
+            continue;
+        }
+        let Some(path) = module_path(tool_dir, &name) else {
+            continue;
+        };
+        if path == main_lua {
+            continue;
+        }
+        let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+        let code = read(&path)?;
+        pending.extend(requires(&code, &path)?);
+        modules.insert(name, (relative, code));
+    }
+
+    let mut code = String::new();
+    let mut sections = Vec::new();
+    for (name, (relative, module)) in &modules {
+        let module = module.trim_end();
+        code.push_str(&format!(
+            "-- {}\npackage.preload[\"{name}\"] = function(...)\n",
+            relative.display()
+        ));
+        sections.push(Section {
+            source: relative.clone(),
+            line: line_count(&code) + 1,
+            lines: module.lines().count(),
+        });
+        code.push_str(module);
+        code.push_str("\nend\n\n");
+    }
+    sections.push(Section {
+        source: main_lua
+            .strip_prefix(root)
+            .unwrap_or(main_lua)
+            .to_path_buf(),
+        line: line_count(&code) + 1,
+        lines: main.lines().count(),
+    });
+    code.push_str(&main);
+    if source_map {
+        code = unbundle_helper(&mut sections) + &code;
+    }
+    Ok(Bundle {
+        code,
+        modules: modules.into_values().map(|(path, _)| path).collect(),
+        sections,
+    })
+}
+
+fn line_count(code: &str) -> usize {
+    code.matches('\n').count()
+}
+
+const HELPER_START: &str = "\
+-- Added by rnplug bundle: rewrites main.lua:<line> in debug.traceback output, or
+-- in any text passed to rnplug_unbundle, to the file and line it came from.
+local rnplug_sections = {
+";
+
+const HELPER_END: &str = "\
+}
+function rnplug_unbundle(text)
+  return (string.gsub(tostring(text), \"main%.lua:(%d+)\", function(line)
+    line = tonumber(line)
+    for _, section in ipairs(rnplug_sections) do
+      if line >= section[1] and line <= section[2] then
+        return section[3] .. \":\" .. (line - section[1] + 1)
+      end
+    end
+  end))
+end
+local rnplug_traceback = debug.traceback
+debug.traceback = function(...)
+  return rnplug_unbundle(rnplug_traceback(...))
+end
+
+";
+
+/// Lua that maps lines of the bundle back to their `sections`, which are moved
+/// down past it.
+fn unbundle_helper(sections: &mut [Section]) -> String {
+    let shift = line_count(HELPER_START) + sections.len() + line_count(HELPER_END);
+    let mut helper = HELPER_START.to_string();
+    for section in sections.iter_mut() {
+        section.line += shift;
+        helper.push_str(&format!(
+            "  {{ {}, {}, {} }},\n",
+            section.line,
+            section.line + section.lines - 1,
+            lua_string(&section.source.display().to_string())
+        ));
+    }
+    helper.push_str(HELPER_END);
+    helper
+}
+
+/// The file `require(name)` loads from the tool folder `dir`: `a/b.lua` or
+/// `a/b/init.lua` for `a.b`.
+fn module_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || "._-".contains(c))
+    {
+        return None;
+    }
+    let base = name.replace('.', "/");
+    [format!("{base}.lua"), format!("{base}/init.lua")]
+        .into_iter()
+        .map(|relative| dir.join(relative))
+        .find(|path| path.is_file())
+}
+
+/// Names passed as a plain string to `require` in `code`.
+fn requires(code: &str, path: &Path) -> Result<Vec<String>, String> {
+    let code = code.strip_prefix('\u{feff}').unwrap_or(code);
+    let ast = parse_fallible(code, LuaVersion::lua51())
+        .into_result()
+        .map_err(|_| {
+            let name = path.display().to_string();
+            format!(
+                "cannot bundle, syntax errors:\n  {}",
+                syntax_errors(&name, code).join("\n  ")
+            )
+        })?;
+    let mut found = Requires(Vec::new());
+    found.visit_ast(&ast);
+    Ok(found.0)
+}
+
+struct Requires(Vec<String>);
+
+impl Visitor for Requires {
+    fn visit_function_call(&mut self, call: &FunctionCall) {
+        let Prefix::Name(name) = call.prefix() else {
+            return;
+        };
+        if !matches!(name.token_type(), TokenType::Identifier { identifier } if identifier.as_str() == "require")
+        {
+            return;
+        }
+        let Some(Suffix::Call(Call::AnonymousCall(args))) = call.suffixes().next() else {
+            return;
+        };
+        let token = match args.as_ref() {
+            FunctionArgs::String(token) => Some(token),
+            FunctionArgs::Parentheses { arguments, .. } if arguments.len() == 1 => {
+                match arguments.iter().next() {
+                    Some(Expression::String(token)) => Some(token),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        if let Some(literal) = token.and_then(string_literal) {
+            self.0.push(literal);
+        }
+    }
+}
+
+fn string_literal(token: &TokenReference) -> Option<String> {
+    match token.token_type() {
+        TokenType::StringLiteral { literal, .. } => Some(literal.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inlines_overridden_modules() {
+        let root = std::env::temp_dir().join(format!("rnplug-bundle-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("lib")).unwrap();
+        fs::write(root.join("main.lua"), "print(require(\"lib.version\"))\n").unwrap();
+        fs::write(root.join("lib/version.lua"), "return \"{{VERSION}}\"\n").unwrap();
+        let overrides = BTreeMap::from([(
+            PathBuf::from("lib/version.lua"),
+            b"return \"1.2\"\n".to_vec(),
+        )]);
+
+        let bundled = bundle(&root, &root.join("main.lua"), &overrides, false).unwrap();
+        assert!(bundled.code.contains("return \"1.2\""), "{}", bundled.code);
+        assert!(!bundled.code.contains("{{VERSION}}"));
+        assert_eq!(bundled.modules, [PathBuf::from("lib/version.lua")]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn resolves_modules_next_to_main_lua() {
+        let root = std::env::temp_dir().join(format!("rnplug-bundle-src-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src/lib/ui")).unwrap();
+        fs::create_dir_all(root.join("lib")).unwrap();
+        fs::write(root.join("src/main.lua"), "require(\"lib.foo\")\n").unwrap();
+        fs::write(root.join("src/lib/foo.lua"), "require(\"lib.ui\")\n").unwrap();
+        fs::write(root.join("src/lib/ui/init.lua"), "return {}\n").unwrap();
+        // outside the tool folder, Renoise would never load it
+        fs::write(root.join("lib/foo.lua"), "error(\"wrong foo\")\n").unwrap();
+
+        let bundled = bundle(&root, &root.join("src/main.lua"), &BTreeMap::new(), false).unwrap();
+        assert!(!bundled.code.contains("wrong foo"), "{}", bundled.code);
+        assert_eq!(
+            bundled.modules,
+            [
+                PathBuf::from("src/lib/foo.lua"),
+                PathBuf::from("src/lib/ui/init.lua")
+            ]
+        );
+        assert_eq!(
+            bundled.sections.last().unwrap().source,
+            PathBuf::from("src/main.lua")
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,23 @@
+use crate::version::parse_version;
+use semver::Version;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+/// `CHANGELOG.md` or `changelog.md` in `root`, whichever exists.
+pub fn changelog_path(root: &Path) -> Option<PathBuf> {
+    ["CHANGELOG.md", "changelog.md"]
+        .into_iter()
+        .map(|name| root.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Whether a heading of the changelog names `version`, e.g. `## [1.3.0] - 2026-05-01`
+/// or `# v1.3`. Build metadata is ignored.
+pub fn has_entry(changelog: &str, version: &Version) -> bool {
+    changelog
+        .lines()
+        .filter(|line| line.trim_start().starts_with('#'))
+        .flat_map(|line| line.split(|c: char| !(c.is_ascii_alphanumeric() || ".-+".contains(c))))
+        .filter_map(|word| parse_version(word.trim_start_matches(['v', 'V'])).ok())
+        .any(|v| v.cmp_precedence(version) == Ordering::Equal)
+}
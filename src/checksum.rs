@@ -0,0 +1,60 @@
+use crate::config::{Checksum, Settings};
+use sha2::{Digest, Sha256, Sha512};
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+/// Combined list of the SHA-256 of every archive in the release directory.
+pub const SUMS_FILE: &str = "SHA256SUMS";
+
+/// Hex digest of the file at `path`.
+pub fn digest(kind: Checksum, path: &Path) -> io::Result<String> {
+    fn hex<D: Digest + io::Write>(mut hasher: D, path: &Path) -> io::Result<String> {
+        io::copy(&mut File::open(path)?, &mut hasher)?;
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect())
+    }
+    match kind {
+        Checksum::Sha256 => hex(Sha256::new(), path),
+        Checksum::Sha512 => hex(Sha512::new(), path),
+    }
+}
+
+/// Writes `<archive>.sha256` (and the other configured checksums) in the
+/// format of `sha256sum`, and refreshes [`SUMS_FILE`] if enabled.
+pub fn write(settings: &Settings, archive: &Path) -> io::Result<()> {
+    let file_name = archive.file_name().unwrap_or_default().to_string_lossy();
+    for kind in settings.checksums() {
+        let line = format!("{}  {file_name}\n", digest(kind, archive)?);
+        let mut path = archive.as_os_str().to_owned();
+        path.push(format!(".{}", kind.extension()));
+        fs::write(path, line)?;
+    }
+    if settings.checksum_list == Some(true)
+        && let Some(dir) = archive.parent()
+    {
+        write_sums(dir)?;
+    }
+    Ok(())
+}
+
+/// Rewrites [`SUMS_FILE`] in `dir` with every `.xrnx` in it, sorted by name.
+fn write_sums(dir: &Path) -> io::Result<()> {
+    let mut archives = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if name.ends_with(".xrnx") {
+            archives.push(name);
+        }
+    }
+    archives.sort();
+    let mut sums = String::new();
+    for name in archives {
+        let hash = digest(Checksum::Sha256, &dir.join(&name))?;
+        sums.push_str(&format!("{hash}  {name}\n"));
+    }
+    fs::write(dir.join(SUMS_FILE), sums)
+}
@@ -0,0 +1,489 @@
+use crate::config::{self, Settings};
+use crate::renoise::ApiVersion;
+use crate::version::BumpKind;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use clap_complete::engine::{ArgValueCompleter, CompletionCandidate};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Parser)]
+#[command(name = "rnplug", version, about = "Package and maintain Renoise tools")]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Args)]
+pub struct GlobalArgs {
+    /// Run as if started in this directory; other paths are relative to it
+    #[arg(short = 'C', global = true, value_name = "DIR")]
+    pub directory: Option<PathBuf>,
+
+    /// Path to the tool's manifest.xml
+    #[arg(long, global = true, default_value = "manifest.xml")]
+    pub manifest: PathBuf,
+
+    /// Print every step as it happens
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
+    /// Apply a `[profile.<name>]` section of rnplug.toml
+    #[arg(long, global = true, add = ArgValueCompleter::new(profile_candidates))]
+    pub profile: Option<String>,
+}
+
+/// Completes `--profile` with the profiles of the rnplug.toml in the current
+/// directory, read each time the shell asks.
+fn profile_candidates(current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy();
+    let Ok(config) = config::load(Path::new(".")) else {
+        return Vec::new();
+    };
+    config
+        .profile
+        .into_keys()
+        .filter(|name| name.starts_with(current.as_ref()))
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+impl GlobalArgs {
+    /// Directory holding the tool sources, i.e. the one containing the manifest.
+    pub fn project_root(&self) -> &Path {
+        match self.manifest.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        }
+    }
+
+    /// Project settings from rnplug.toml with `--profile` applied.
+    pub fn settings(&self) -> Result<Settings, config::ConfigError> {
+        config::load(self.project_root())?.resolve(self.profile.as_deref())
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Bump the manifest version and build release/<id>.xrnx (default)
+    Package(PackageArgs),
+    /// Scaffold a new tool (manifest.xml, main.lua, README.md)
+    Init(InitArgs),
+    /// Copy the packaged tool into the Renoise Scripts/Tools directory
+    Install(InstallArgs),
+    /// Check the manifest and project layout without changing anything
+    Validate,
+    /// Bump the manifest version without building an archive
+    Bump(BumpArgs),
+    /// Rebuild the archive whenever sources or the manifest change (no version bump)
+    Watch(WatchArgs),
+    /// Remove built archives from release/ and leftovers from interrupted runs
+    Clean(CleanArgs),
+    /// Extract an existing .xrnx archive
+    Unpack(UnpackArgs),
+    /// Show the manifest and file list of an .xrnx archive
+    Inspect(InspectArgs),
+    /// Compare the files and manifest of two .xrnx archives
+    Diff(DiffArgs),
+    /// Tag the current version and upload release/<id>.xrnx as a release
+    Publish(PublishArgs),
+    /// Check the project and environment for common problems
+    Doctor,
+    /// Print a shell completion script
+    Completions(CompletionsArgs),
+    /// Replace this executable with the latest released binary
+    SelfUpdate(SelfUpdateArgs),
+    /// Read or edit a single manifest field in place
+    Manifest(ManifestArgs),
+    /// Check the detached signature of an .xrnx archive
+    Verify(VerifyArgs),
+    /// Inline the project modules main.lua requires and package it as a single file
+    Bundle(BundleArgs),
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        Command::Package(PackageArgs::default())
+    }
+}
+
+#[derive(Debug, Default, Args)]
+pub struct PackageArgs {
+    #[command(flatten)]
+    pub bump: BumpArgs,
+
+    /// Package even if the version is not newer than the last release, replacing
+    /// an archive of the same version
+    #[arg(long)]
+    pub force: bool,
+
+    /// Build the next pre-release on this channel's own version stream, leaving
+    /// the manifest on disk untouched
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with_all = [
+            "conventional",
+            "version_from",
+            "set_version",
+            "promote",
+            "pre",
+            "no_bump",
+            "skip_unchanged"
+        ]
+    )]
+    pub channel: Option<String>,
+
+    /// Do nothing when no file changed since the last release
+    #[arg(long)]
+    pub skip_unchanged: bool,
+
+    /// Fail if the manifest version is behind the highest `v*` tag (the default)
+    #[arg(long, overrides_with = "no_check_tags")]
+    pub check_tags: bool,
+
+    /// Don't compare the manifest version against git tags
+    #[arg(long, overrides_with = "check_tags")]
+    pub no_check_tags: bool,
+
+    /// Renoise version the tool is built for, its ApiVersion is checked against the manifest
+    #[arg(long, value_name = "VERSION")]
+    pub target_renoise: Option<String>,
+
+    /// Turn compatibility warnings into errors
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Write a .zip copy next to each .xrnx archive
+    #[arg(long)]
+    pub also_zip: bool,
+
+    /// Print the size of every file before and after compression
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Print the --stats report as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// How many of the biggest files --stats lists
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    pub top: usize,
+
+    /// Move an existing archive aside, with its modification time in the name,
+    /// instead of replacing it
+    #[arg(long)]
+    pub backup: bool,
+
+    /// Keep the files' modification times instead of a fixed timestamp
+    #[arg(long, conflicts_with = "reproducible")]
+    pub keep_mtimes: bool,
+
+    /// Give every entry the same timestamp (`SOURCE_DATE_EPOCH` or 1980) so the
+    /// archive only depends on the file contents (the default)
+    #[arg(long)]
+    pub reproducible: bool,
+
+    /// Store files without compressing them, for quick dev builds
+    #[arg(long)]
+    pub fast: bool,
+
+    /// Name a dev build's archive like a release, without the `-dev.<shortsha>` suffix
+    #[arg(long)]
+    pub release: bool,
+
+    /// Directory to write the archives to, instead of `output` from rnplug.toml
+    #[arg(long, value_name = "DIR")]
+    pub output: Option<PathBuf>,
+
+    /// Run luacheck over the packaged Lua files and report its warnings, along
+    /// with uses of Renoise API newer than the manifest's ApiVersion
+    #[arg(long)]
+    pub lint: bool,
+
+    /// Fail when linting finds any warning (implies --lint)
+    #[arg(long)]
+    pub deny_warnings: bool,
+
+    /// Sign the archives with the `[signing]` key from rnplug.toml
+    #[arg(long)]
+    pub sign: bool,
+
+    /// Package without a top-level main.lua, for library bundles Renoise doesn't run
+    #[arg(long)]
+    pub allow_missing_entry: bool,
+}
+
+#[derive(Debug, Default, Args)]
+pub struct BumpArgs {
+    /// Bump the major version
+    #[arg(long, group = "bump_kind")]
+    pub major: bool,
+
+    /// Bump the minor version (default)
+    #[arg(long, group = "bump_kind")]
+    pub minor: bool,
+
+    /// Bump the patch version
+    #[arg(long, group = "bump_kind")]
+    pub patch: bool,
+
+    /// Infer the bump from Conventional Commits since the last `v*` tag
+    #[arg(long, group = "bump_kind")]
+    pub conventional: bool,
+
+    /// Take the version from somewhere other than the manifest
+    #[arg(long, value_name = "SOURCE", group = "bump_kind")]
+    pub version_from: Option<VersionSource>,
+
+    /// With --version-from git-tag or `bump = "git-tag"`, append the number of
+    /// commits since the tag as build metadata
+    #[arg(long)]
+    pub commit_count: bool,
+
+    /// Use this exact version instead of bumping
+    #[arg(long, value_name = "VERSION", group = "bump_kind")]
+    pub set_version: Option<String>,
+
+    /// Accept a --set-version that is not newer than the current one
+    #[arg(long, requires = "set_version")]
+    pub allow_downgrade: bool,
+
+    /// Move a pre-release on to the next channel, or to the final release after the last one
+    #[arg(long, group = "bump_kind", conflicts_with = "pre")]
+    pub promote: bool,
+
+    /// Produce or increment a pre-release on this channel (alpha, beta, rc, ...)
+    #[arg(long, value_name = "CHANNEL", conflicts_with_all = ["set_version", "version_from"])]
+    pub pre: Option<String>,
+
+    /// Don't reset lower components when bumping, e.g. 1.3.2 -> 1.4.2
+    #[arg(long, conflicts_with_all = ["set_version", "no_bump"])]
+    pub keep_patch: bool,
+
+    /// Keep the current version (build metadata is still applied)
+    #[arg(long, conflicts_with_all = ["bump_kind", "pre"])]
+    pub no_bump: bool,
+
+    /// Append `+<shortsha>.<yyyymmdd>` for dev builds; without it any build metadata is stripped
+    #[arg(long, value_name = "SOURCE")]
+    pub build_meta: Option<BuildMeta>,
+
+    /// Print the version change (and archive name) without writing anything
+    #[arg(long)]
+    pub plan: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum VersionSource {
+    /// The most recent `v*` tag reachable from HEAD
+    GitTag,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum BuildMeta {
+    /// Short commit hash of HEAD and today's date
+    Git,
+}
+
+impl BumpArgs {
+    /// Whether the command line decides the new version, overriding the
+    /// configured strategy.
+    pub fn selects_version(&self) -> bool {
+        self.explicit_kind().is_some()
+            || self.conventional
+            || self.version_from.is_some()
+            || self.set_version.is_some()
+            || self.promote
+            || self.no_bump
+    }
+
+    /// The bump kind if one was given on the command line.
+    pub fn explicit_kind(&self) -> Option<BumpKind> {
+        if self.major {
+            Some(BumpKind::Major)
+        } else if self.minor {
+            Some(BumpKind::Minor)
+        } else if self.patch {
+            Some(BumpKind::Patch)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct InitArgs {
+    /// Reverse-domain tool Id, e.g. com.example.MyTool
+    pub id: String,
+
+    /// Template name from the templates directory, or a git URL to clone
+    #[arg(long)]
+    pub template: Option<String>,
+
+    /// Author written into the manifest, defaults to `git config user.name`
+    #[arg(long)]
+    pub author: Option<String>,
+
+    /// Display name, defaults to the last segment of the Id
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Scripting ApiVersion, defaults to the one of the newest Renoise
+    #[arg(long)]
+    pub api_version: Option<ApiVersion>,
+
+    /// Tool category shown in Renoise
+    #[arg(long)]
+    pub category: Option<String>,
+
+    /// One-line description for the manifest, defaults to "<name> for Renoise"
+    #[arg(long)]
+    pub description: Option<String>,
+
+    /// Don't ask for missing values, use the defaults
+    #[arg(long)]
+    pub non_interactive: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct InstallArgs {
+    /// Install into this directory instead of the detected Scripts/Tools folder
+    #[arg(long)]
+    pub tools_dir: Option<PathBuf>,
+
+    /// Target a specific installed Renoise version (e.g. 3.4.3) instead of the newest
+    #[arg(long)]
+    pub renoise_version: Option<String>,
+    /// Install the last build on this channel, see `package --channel`
+    #[arg(long, value_name = "NAME")]
+    pub channel: Option<String>,
+
+    /// Install the dev build of the current commit, as `watch` makes them
+    #[arg(long, conflicts_with = "channel")]
+    pub dev: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct WatchArgs {
+    /// Quiet period in milliseconds before a change triggers a rebuild
+    #[arg(long, default_value_t = 300)]
+    pub debounce: u64,
+
+    /// Store files without compressing them, builds finish quicker
+    #[arg(long)]
+    pub fast: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct CleanArgs {
+    /// Only list what would be deleted
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct UnpackArgs {
+    /// The .xrnx archive to extract
+    pub archive: PathBuf,
+
+    /// Destination directory (defaults to the archive name without extension)
+    pub dest: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct InspectArgs {
+    /// The .xrnx archive to inspect
+    pub archive: PathBuf,
+
+    /// Print machine-readable JSON instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// The older archive
+    pub old: PathBuf,
+
+    /// The newer archive
+    pub new: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct PublishArgs {
+    /// Create a GitHub release (token read from GITHUB_TOKEN or GH_TOKEN)
+    #[arg(long)]
+    pub github: bool,
+
+    /// `owner/repo` to publish to, defaults to the `origin` remote
+    #[arg(long)]
+    pub repo: Option<String>,
+
+    /// Create the release as a draft
+    #[arg(long)]
+    pub draft: bool,
+    /// Publish the last build on this channel, see `package --channel`
+    #[arg(long, value_name = "NAME")]
+    pub channel: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct CompletionsArgs {
+    /// Shell to register completions for
+    pub shell: Shell,
+}
+
+#[derive(Debug, Args)]
+pub struct SelfUpdateArgs {
+    /// Only report whether a newer version is available
+    #[arg(long)]
+    pub check: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct VerifyArgs {
+    /// The signed .xrnx archive; its signature is the .minisig or .asc next to it
+    pub archive: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct BundleArgs {
+    /// Print the bundled main.lua instead of packaging it
+    #[arg(long)]
+    pub print: bool,
+
+    /// Make errors point at the original files and, when packaging, write
+    /// `<archive>.map` with the bundled line ranges of each file
+    #[arg(long)]
+    pub source_map: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ManifestArgs {
+    #[command(subcommand)]
+    pub action: ManifestAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ManifestAction {
+    /// Print the value of a field
+    Get {
+        /// Element name, e.g. Author
+        field: String,
+    },
+    /// Set a field, adding it if missing
+    Set {
+        /// Element name, e.g. Author
+        field: String,
+        value: String,
+    },
+    /// Remove a field
+    Unset {
+        /// Element name, e.g. Homepage
+        field: String,
+    },
+}
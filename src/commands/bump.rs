@@ -0,0 +1,18 @@
+use crate::bump::bump_manifest;
+use crate::cli::{BumpArgs, GlobalArgs};
+
+pub fn run(global: &GlobalArgs, args: &BumpArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let bumped = bump_manifest(global, &global.settings()?, args)?;
+    if args.plan {
+        println!(
+            "{}: {} -> {}",
+            bumped.tool_id, bumped.old_version, bumped.new_version
+        );
+        return Ok(());
+    }
+    println!(
+        "Bumped {} from {} to {}",
+        bumped.tool_id, bumped.old_version, bumped.new_version
+    );
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use crate::bundle::bundle;
+use crate::cli::{BundleArgs, GlobalArgs};
+use crate::commands::package;
+use std::fs;
+use std::path::PathBuf;
+
+pub fn run(global: &GlobalArgs, args: &BundleArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let root = global.project_root();
+    let mut settings = global.settings()?;
+    let main_lua = settings.main_lua(root);
+    let mut overrides = package::build_overrides(global, &settings)?;
+    let bundled = bundle(root, &main_lua, &overrides, args.source_map)?;
+    if args.print {
+        print!("{}", bundled.code);
+        return Ok(());
+    }
+    if global.verbose {
+        for module in &bundled.modules {
+            println!("  inlining {}", module.display());
+        }
+    }
+
+    settings.exclude.extend(bundled.modules.iter().cloned());
+    let relative = main_lua
+        .strip_prefix(root)
+        .unwrap_or(&main_lua)
+        .to_path_buf();
+    overrides.insert(relative, bundled.code.into_bytes());
+    let path = package::build(global, &settings, overrides, false)?;
+    if args.source_map {
+        let mut map = path.clone().into_os_string();
+        map.push(".map");
+        let map = PathBuf::from(map);
+        let sections = serde_json::json!({ "file": "main.lua", "sections": bundled.sections });
+        fs::write(&map, serde_json::to_string_pretty(&sections)? + "\n")?;
+        println!("Created {}", map.display());
+    }
+    println!(
+        "Created {} with {} module(s) inlined into main.lua",
+        path.display(),
+        bundled.modules.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,333 @@
+use crate::archive::TEMP_PREFIX;
+use crate::checksum::SUMS_FILE;
+use crate::cli::{CleanArgs, GlobalArgs};
+use crate::config::Artifact;
+use crate::manifest::read_file;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub fn run(global: &GlobalArgs, args: &CleanArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let root = global.project_root();
+    let settings = global.settings()?;
+    let release_dir = settings.release_dir(root);
+    // without a readable manifest any id matches, the rest of the name still has to
+    let artifact = read_file(&global.manifest)
+        .ok()
+        .and_then(|(contents, _)| Artifact::of(&contents).ok());
+    let pattern = ArtifactPattern::new(
+        settings.artifact_name.as_deref().unwrap_or("{id}.xrnx"),
+        artifact.as_ref(),
+    );
+    let targets = targets(root, &release_dir, &pattern)?;
+
+    if targets.is_empty() {
+        println!("Nothing to clean");
+        return Ok(());
+    }
+
+    for path in &targets {
+        if args.dry_run {
+            println!("Would remove {}", path.display());
+            continue;
+        }
+        if is_staging(path) && path.is_dir() {
+            fs::remove_dir_all(path)?;
+        } else if path.is_dir() {
+            fs::remove_dir(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+        println!("Removed {}", path.display());
+    }
+    Ok(())
+}
+
+/// Files rnplug wrote to `release_dir` (and its channel folders) or left in
+/// `root`, staging folders included, followed by the folders that are empty
+/// without them. Refuses a release directory that is the project root or
+/// contains it.
+fn targets(
+    root: &Path,
+    release_dir: &Path,
+    pattern: &ArtifactPattern,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut targets = Vec::new();
+    if release_dir.exists() {
+        if root
+            .canonicalize()?
+            .starts_with(release_dir.canonicalize()?)
+        {
+            return Err(format!(
+                "refusing to clean {}, it holds the project itself",
+                release_dir.display()
+            )
+            .into());
+        }
+        generated_in(release_dir, pattern, &mut targets)?;
+    }
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        if is_staging(&path) && !targets.contains(&path) {
+            targets.push(path);
+        }
+    }
+    Ok(targets)
+}
+
+/// Adds the generated files and staging folders in `dir`, recursively, to
+/// `targets`, and `dir` itself when nothing else is left in it. Returns whether
+/// it was added.
+fn generated_in(
+    dir: &Path,
+    pattern: &ArtifactPattern,
+    targets: &mut Vec<PathBuf>,
+) -> io::Result<bool> {
+    let mut kept = false;
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() && is_staging(&path) {
+            targets.push(path);
+        } else if file_type.is_dir() {
+            kept |= !generated_in(&path, pattern, targets)?;
+        } else if file_type.is_file() && is_generated(&entry.file_name().to_string_lossy(), pattern)
+        {
+            targets.push(path);
+        } else {
+            kept = true;
+        }
+    }
+    if !kept {
+        targets.push(dir.to_path_buf());
+    }
+    Ok(!kept)
+}
+
+/// Whether `path` is an unfinished archive or a staging folder.
+fn is_staging(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with(TEMP_PREFIX))
+}
+
+/// Whether `name` is one of the files packaging writes: archives named after
+/// `pattern` and their `.zip` copies, with checksums, signatures and source
+/// maps, the checksum list and unfinished archives.
+fn is_generated(name: &str, pattern: &ArtifactPattern) -> bool {
+    if name.starts_with(TEMP_PREFIX) || name == SUMS_FILE {
+        return true;
+    }
+    let name = [".sha256", ".sha512", ".minisig", ".asc", ".map"]
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(name);
+    name.strip_suffix(".xrnx")
+        .or_else(|| name.strip_suffix(".zip"))
+        .is_some_and(|stem| pattern.matches(stem))
+}
+
+/// Archive names an `artifact_name` template produces, without the extension:
+/// the tool's id and name as they are, anything for the version, channel and
+/// platform, and optionally a `-<suffix>` as channel, platform and dev builds
+/// get when the template doesn't mention them.
+struct ArtifactPattern {
+    /// Literal text between the placeholders.
+    pieces: Vec<String>,
+}
+
+impl ArtifactPattern {
+    fn new(template: &str, artifact: Option<&Artifact>) -> Self {
+        let stem = template.strip_suffix(".xrnx").unwrap_or(template);
+        let stem = match artifact {
+            Some(artifact) => stem
+                .replace("{id}", &artifact.id)
+                .replace("{name}", artifact.name.as_deref().unwrap_or(&artifact.id)),
+            None => stem.to_string(),
+        };
+        let stem = ["{id}", "{name}", "{version}", "{channel}", "{platform}"]
+            .iter()
+            .fold(stem, |stem, placeholder| stem.replace(placeholder, "\0"));
+        ArtifactPattern {
+            pieces: stem.split('\0').map(String::from).collect(),
+        }
+    }
+
+    fn matches(&self, stem: &str) -> bool {
+        let Some((first, rest)) = self.pieces.split_first() else {
+            return false;
+        };
+        let Some(mut stem) = stem.strip_prefix(first.as_str()) else {
+            return false;
+        };
+        for (i, piece) in rest.iter().enumerate() {
+            // the last piece is searched from the end, so the placeholder before
+            // it can take up as much as it needs
+            let found = if i + 1 == rest.len() {
+                stem.rfind(piece.as_str())
+            } else {
+                stem.find(piece.as_str())
+            };
+            let Some(at) = found else {
+                return false;
+            };
+            stem = &stem[at + piece.len()..];
+        }
+        stem.is_empty() || stem.starts_with('-')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Command};
+    use clap::Parser;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rnplug-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn tool() -> Artifact {
+        Artifact {
+            id: "com.example.Tool".to_string(),
+            name: Some("Tool".to_string()),
+            ..Artifact::default()
+        }
+    }
+
+    #[test]
+    fn recognizes_generated_files() {
+        let pattern = ArtifactPattern::new("{id}.xrnx", Some(&tool()));
+        for name in [
+            "com.example.Tool.xrnx",
+            "com.example.Tool-beta.xrnx.sha256",
+            "com.example.Tool-linux-dev.1a2b3c4.xrnx",
+            "com.example.Tool.zip.minisig",
+            "com.example.Tool.xrnx.map",
+            "SHA256SUMS",
+            ".rnplug-tmp-x",
+        ] {
+            assert!(is_generated(name, &pattern), "{name}");
+        }
+        for name in [
+            "main.lua",
+            "manifest.xml",
+            "notes.sha256",
+            "README.md",
+            "com.other.Tool.xrnx",
+            "com.example.ToolKit.xrnx",
+            "com.example.Tool.txt",
+        ] {
+            assert!(!is_generated(name, &pattern), "{name}");
+        }
+    }
+
+    #[test]
+    fn matches_the_configured_artifact_name() {
+        for (template, artifact, name, expected) in [
+            ("{name}-{version}.xrnx", Some(tool()), "Tool-1.2.xrnx", true),
+            (
+                "{name}-{version}.xrnx",
+                Some(tool()),
+                "Tool-1.2-rc.1-beta.zip",
+                true,
+            ),
+            (
+                "{name}-{version}.xrnx",
+                Some(tool()),
+                "Other-1.2.xrnx",
+                false,
+            ),
+            ("{name}-{version}.xrnx", Some(tool()), "Tool.xrnx", false),
+            (
+                "{name}_{platform}_v{version}.xrnx",
+                Some(tool()),
+                "Tool_mac_v2.0.xrnx",
+                true,
+            ),
+            (
+                "{name}_{platform}_v{version}.xrnx",
+                Some(tool()),
+                "Tool_mac.xrnx",
+                false,
+            ),
+            (
+                "v{version}-{id}.xrnx",
+                Some(tool()),
+                "v1-com.example.Tool.xrnx",
+                true,
+            ),
+            ("{id}.xrnx", None, "anything.xrnx", true),
+            ("release-{id}.xrnx", None, "plugin.xrnx", false),
+        ] {
+            let pattern = ArtifactPattern::new(template, artifact.as_ref());
+            assert_eq!(is_generated(name, &pattern), expected, "{template} {name}");
+        }
+    }
+
+    #[test]
+    fn only_removes_generated_files() {
+        let root = scratch_dir("clean");
+        let release = root.join("release");
+        fs::create_dir_all(release.join("beta")).unwrap();
+        fs::write(release.join("com.example.Tool.xrnx"), "").unwrap();
+        fs::write(release.join("notes.txt"), "").unwrap();
+        fs::write(release.join("third-party.xrnx"), "").unwrap();
+        fs::write(release.join("beta/com.example.Tool-beta.xrnx"), "").unwrap();
+        fs::write(root.join("main.lua"), "").unwrap();
+
+        let pattern = ArtifactPattern::new("{id}.xrnx", Some(&tool()));
+        let targets = targets(&root, &release, &pattern).unwrap();
+        assert_eq!(
+            targets,
+            [
+                release.join("beta/com.example.Tool-beta.xrnx"),
+                release.join("beta"),
+                release.join("com.example.Tool.xrnx"),
+            ]
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn removes_staging_folders() {
+        let root = scratch_dir("clean-staging");
+        let release = root.join("release");
+        let staging = release.join(format!("{TEMP_PREFIX}com.example.Tool"));
+        fs::create_dir_all(staging.join("lib")).unwrap();
+        fs::write(staging.join("lib/util.lua"), "").unwrap();
+        fs::create_dir_all(root.join(format!("{TEMP_PREFIX}old"))).unwrap();
+        fs::write(root.join(format!("{TEMP_PREFIX}old.xrnx")), "").unwrap();
+        fs::write(root.join("main.lua"), "").unwrap();
+
+        let manifest = root.join("manifest.xml");
+        let cli =
+            Cli::try_parse_from(["rnplug", "--manifest", manifest.to_str().unwrap(), "clean"])
+                .unwrap();
+        let Some(Command::Clean(args)) = &cli.command else {
+            unreachable!()
+        };
+        run(&cli.global, args).unwrap();
+        assert!(!release.exists());
+        let left: Vec<_> = fs::read_dir(&root)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(left, ["main.lua"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn refuses_the_project_root() {
+        let root = scratch_dir("clean-root");
+        fs::write(root.join("main.lua"), "").unwrap();
+        let pattern = ArtifactPattern::new("{id}.xrnx", None);
+        assert!(targets(&root, &root, &pattern).is_err());
+        assert!(targets(&root, root.parent().unwrap(), &pattern).is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
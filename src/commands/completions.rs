@@ -0,0 +1,19 @@
+use crate::cli::{Cli, CompletionsArgs, GlobalArgs};
+use clap::CommandFactory;
+use clap_complete::env::Shells;
+use std::io;
+
+/// Prints the script registering the completions with the shell. The shell
+/// then asks `rnplug` itself for candidates, so they follow the current
+/// project, such as the profiles in its rnplug.toml.
+pub fn run(_global: &GlobalArgs, args: &CompletionsArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = Cli::command();
+    let name = cmd.get_name();
+    let shell = args.shell.to_string();
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(&shell)
+        .ok_or_else(|| format!("no dynamic completions for {shell}"))?;
+    completer.write_registration("COMPLETE", name, name, name, &mut io::stdout())?;
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use crate::archive::{archive_manifest, entry_hashes};
+use crate::cli::{DiffArgs, GlobalArgs};
+use crate::manifest::read_manifest;
+use std::collections::BTreeSet;
+use std::fs::File;
+
+pub fn run(_global: &GlobalArgs, args: &DiffArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut old_zip = zip::ZipArchive::new(File::open(&args.old)?)?;
+    let mut new_zip = zip::ZipArchive::new(File::open(&args.new)?)?;
+
+    let old_manifest = read_manifest(&archive_manifest(&mut old_zip, &args.old)?)?;
+    let new_manifest = read_manifest(&archive_manifest(&mut new_zip, &args.new)?)?;
+
+    let mut changes = 0;
+    println!("Manifest:");
+    let mut fields: Vec<(String, Option<String>, Option<String>)> = old_manifest
+        .fields()
+        .into_iter()
+        .zip(new_manifest.fields())
+        .map(|((field, old), (_, new))| (field.to_string(), old, new))
+        .collect();
+    let unknown: BTreeSet<&String> = old_manifest
+        .other
+        .keys()
+        .chain(new_manifest.other.keys())
+        .collect();
+    for name in unknown {
+        let old = old_manifest.other.get(name).cloned();
+        let new = new_manifest.other.get(name).cloned();
+        fields.push((name.clone(), old, new));
+    }
+    for (field, old, new) in fields {
+        if old != new {
+            changes += 1;
+            println!(
+                "  {field}: {} -> {}",
+                old.as_deref().unwrap_or("(none)"),
+                new.as_deref().unwrap_or("(none)")
+            );
+        }
+    }
+
+    let old_files = entry_hashes(&mut old_zip)?;
+    let new_files = entry_hashes(&mut new_zip)?;
+    println!("Files:");
+    for (name, hash) in &new_files {
+        match old_files.get(name) {
+            None => println!("  + {name}"),
+            Some(old_hash) if old_hash != hash => println!("  ~ {name}"),
+            Some(_) => continue,
+        }
+        changes += 1;
+    }
+    for name in old_files.keys().filter(|n| !new_files.contains_key(*n)) {
+        println!("  - {name}");
+        changes += 1;
+    }
+
+    if changes == 0 {
+        println!("No differences");
+    }
+    Ok(())
+}
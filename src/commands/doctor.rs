@@ -0,0 +1,125 @@
+use crate::archive::TEMP_PREFIX;
+use crate::cli::GlobalArgs;
+use crate::config::Settings;
+use crate::manifest::{parse_manifest, read_file};
+use crate::renoise;
+use std::env;
+use std::fs;
+use std::process::{Command, Stdio};
+
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+pub fn run(global: &GlobalArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut failures = 0;
+    let mut report = |status: Status, message: String| {
+        let label = match status {
+            Status::Ok => "ok  ",
+            Status::Warn => "warn",
+            Status::Fail => {
+                failures += 1;
+                "FAIL"
+            }
+        };
+        println!("[{label}] {message}");
+    };
+
+    match read_file(&global.manifest) {
+        Ok((contents, _)) => match parse_manifest(&contents) {
+            Ok((id, version)) => report(Status::Ok, format!("manifest: {id} {version}")),
+            Err(e) => report(Status::Fail, format!("manifest: {e}")),
+        },
+        Err(e) => report(
+            Status::Fail,
+            format!("manifest: cannot read {}: {e}", global.manifest.display()),
+        ),
+    }
+
+    match renoise::version_dirs().first() {
+        Some((version, dir)) => report(
+            Status::Ok,
+            format!("Renoise {version} preferences at {}", dir.display()),
+        ),
+        None => report(
+            Status::Warn,
+            "Renoise preferences not found, `install` needs --tools-dir".to_string(),
+        ),
+    }
+    match renoise::tools_dir(None) {
+        Some(dir) if dir.is_dir() => {
+            report(Status::Ok, format!("tools directory {}", dir.display()))
+        }
+        Some(dir) => report(
+            Status::Warn,
+            format!("tools directory {} does not exist yet", dir.display()),
+        ),
+        None => {}
+    }
+
+    let settings = match global.settings() {
+        Ok(settings) => settings,
+        Err(e) => {
+            report(Status::Fail, e.to_string());
+            Settings::default()
+        }
+    };
+
+    // probe the project root when release/ doesn't exist yet, it will be created there
+    let release_dir = settings.release_dir(global.project_root());
+    let output_dir = if release_dir.is_dir() {
+        release_dir
+    } else {
+        global.project_root().to_path_buf()
+    };
+    let probe = output_dir.join(format!("{TEMP_PREFIX}probe"));
+    match fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            report(Status::Ok, format!("{} is writable", output_dir.display()));
+        }
+        Err(e) => report(
+            Status::Fail,
+            format!("{} is not writable: {e}", output_dir.display()),
+        ),
+    }
+
+    for program in ["git", "luacheck"] {
+        if available(program, &["--version"]) {
+            report(Status::Ok, format!("{program} found"));
+        } else {
+            report(Status::Warn, format!("{program} not found on PATH"));
+        }
+    }
+
+    let token = env::var_os("GITHUB_TOKEN").is_some()
+        || env::var_os("GH_TOKEN").is_some()
+        || available("gh", &["auth", "token"]);
+    if token {
+        report(
+            Status::Ok,
+            "GitHub token available for `publish`".to_string(),
+        );
+    } else {
+        report(
+            Status::Warn,
+            "no GitHub token (GITHUB_TOKEN, GH_TOKEN or `gh auth`)".to_string(),
+        );
+    }
+
+    if failures > 0 {
+        return Err(format!("{failures} check(s) failed").into());
+    }
+    Ok(())
+}
+
+fn available(program: &str, args: &[&str]) -> bool {
+    Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
@@ -0,0 +1,170 @@
+use crate::cli::{GlobalArgs, InitArgs};
+use crate::git::git;
+use crate::placeholders::lua_string;
+use crate::renoise::{self, ApiVersion};
+use crate::scaffold;
+use crate::validate::check_id;
+use quick_xml::escape::partial_escape;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+
+pub fn run(global: &GlobalArgs, args: &InitArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let id = args.id.trim();
+    check_id(id)?;
+    // the last segment of a reverse-domain Id doubles as the display name
+    let default_name = id.rsplit('.').next().unwrap_or(id);
+
+    let root = global.project_root();
+    let interactive = !args.non_interactive && io::stdin().is_terminal();
+    let ask = |label: &str, given: &Option<String>, default: String| match given {
+        Some(value) => Ok(value.clone()),
+        None if interactive => prompt(label, &default),
+        None => Ok(default),
+    };
+
+    let name = ask("Name", &args.name, default_name.to_string())?;
+    let author = ask(
+        "Author",
+        &args.author,
+        git(root, &["config", "user.name"]).unwrap_or_default(),
+    )?;
+    let newest_api = renoise::API_VERSIONS
+        .last()
+        .map_or(ApiVersion::new(6, 0), |(_, api)| *api);
+    let api_version = loop {
+        let answer = ask(
+            "ApiVersion",
+            &args.api_version.map(|v| v.to_string()),
+            newest_api.to_string(),
+        )?;
+        match answer.parse::<ApiVersion>() {
+            Ok(api) => break api.to_string(),
+            Err(e) if interactive => eprintln!("{e}"),
+            Err(e) => return Err(e.into()),
+        }
+    };
+    let category = ask("Category", &args.category, "Tools".to_string())?;
+    let description = ask(
+        "Description",
+        &args.description,
+        format!("{name} for Renoise"),
+    )?;
+
+    let files = match &args.template {
+        Some(spec) => scaffold::load(spec)?,
+        None => scaffold::builtin(),
+    };
+
+    let existing: Vec<_> = files
+        .iter()
+        .map(|f| root.join(&f.path))
+        .filter(|p| p.exists())
+        .collect();
+    if !existing.is_empty() {
+        let paths: Vec<_> = existing.iter().map(|p| p.display().to_string()).collect();
+        return Err(format!("not overwriting existing {}", paths.join(", ")).into());
+    }
+
+    let vars = [
+        ("id", id),
+        ("name", name.as_str()),
+        ("author", author.as_str()),
+        ("api_version", api_version.as_str()),
+        ("category", category.as_str()),
+        ("description", description.as_str()),
+    ];
+    let escaped = |escape: fn(&str) -> String| -> Vec<(&str, String)> {
+        vars.iter()
+            .map(|(key, value)| (*key, escape(value)))
+            .collect()
+    };
+    let plain = escaped(str::to_string);
+    let xml = escaped(|value| partial_escape(value).into_owned());
+    let lua = escaped(|value| {
+        let literal = lua_string(value);
+        literal[1..literal.len() - 1].to_string()
+    });
+    for file in &files {
+        let path = root.join(&file.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // values land in XML text or Lua strings there, elsewhere they are used as typed
+        let escaped = match file.path.extension().and_then(|e| e.to_str()) {
+            Some("xml") => &xml,
+            Some("lua") => &lua,
+            _ => &plain,
+        };
+        let vars: Vec<(&str, &str)> = escaped.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        fs::write(&path, scaffold::render(&file.contents, &vars))?;
+        println!("Created {}", path.display());
+    }
+    Ok(())
+}
+
+/// Asks for a value on the terminal, an empty answer taking `default`.
+fn prompt(label: &str, default: &str) -> io::Result<String> {
+    if default.is_empty() {
+        print!("{label}: ");
+    } else {
+        print!("{label} [{default}]: ");
+    }
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() {
+        default.to_string()
+    } else {
+        answer.to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Command};
+    use crate::lua::syntax_errors;
+    use clap::Parser;
+
+    #[test]
+    fn escapes_names_in_lua_and_xml() {
+        let dir = std::env::temp_dir().join(format!("rnplug-init-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("manifest.xml");
+        let name = r#"My "Quote" \ <Tool>"#;
+        let cli = Cli::try_parse_from([
+            "rnplug",
+            "--manifest",
+            manifest.to_str().unwrap(),
+            "init",
+            "com.example.Quote",
+            "--name",
+            name,
+            "--author",
+            "Jo",
+            "--non-interactive",
+        ])
+        .unwrap();
+        let Some(Command::Init(args)) = &cli.command else {
+            unreachable!();
+        };
+        run(&cli.global, args).unwrap();
+
+        let main_lua = fs::read_to_string(dir.join("main.lua")).unwrap();
+        assert_eq!(syntax_errors("main.lua", &main_lua), Vec::<String>::new());
+        assert!(
+            main_lua.contains(r#""My \"Quote\" \\ <Tool> says hello""#),
+            "{main_lua}"
+        );
+        let (contents, _) = crate::manifest::read_file(&manifest).unwrap();
+        let read = crate::manifest::read_manifest(&contents).unwrap();
+        assert_eq!(read.name.as_deref(), Some(name));
+        assert_eq!(
+            read.description.as_deref(),
+            Some(format!("{name} for Renoise").as_str())
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+use crate::archive::archive_manifest;
+use crate::cli::{GlobalArgs, InspectArgs};
+use crate::manifest::read_manifest;
+use crate::renoise::ApiVersion;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+
+#[derive(Debug, Serialize)]
+struct Report {
+    id: Option<String>,
+    name: Option<String>,
+    version: Option<String>,
+    api_version: Option<ApiVersion>,
+    author: Option<String>,
+    description: Option<String>,
+    category: Option<String>,
+    homepage: Option<String>,
+    icon: Option<String>,
+    platform: Option<String>,
+    auto_upgrade: Option<bool>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    other: BTreeMap<String, String>,
+    /// Build metadata rnplug writes into the archive comment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+    files: Vec<FileEntry>,
+    compressed_size: u64,
+    uncompressed_size: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct FileEntry {
+    name: String,
+    size: u64,
+    compressed_size: u64,
+}
+
+pub fn run(_global: &GlobalArgs, args: &InspectArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut zip = zip::ZipArchive::new(File::open(&args.archive)?)?;
+
+    let manifest = read_manifest(&archive_manifest(&mut zip, &args.archive)?)?;
+    let comment = String::from_utf8_lossy(zip.comment())
+        .trim_end()
+        .to_string();
+
+    let mut files = Vec::new();
+    for i in 0..zip.len() {
+        let entry = zip.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        files.push(FileEntry {
+            name: entry.name().to_string(),
+            size: entry.size(),
+            compressed_size: entry.compressed_size(),
+        });
+    }
+
+    let fields = manifest.all_fields();
+    let report = Report {
+        id: manifest.id,
+        name: manifest.name,
+        version: manifest.version,
+        api_version: manifest.api_version,
+        author: manifest.author,
+        description: manifest.description,
+        category: manifest.category,
+        homepage: manifest.homepage,
+        icon: manifest.icon,
+        platform: manifest.platform,
+        auto_upgrade: manifest.auto_upgrade,
+        other: manifest.other,
+        comment: (!comment.is_empty()).then_some(comment),
+        compressed_size: files.iter().map(|f| f.compressed_size).sum(),
+        uncompressed_size: files.iter().map(|f| f.size).sum(),
+        files,
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_human(&report, &fields);
+    }
+    Ok(())
+}
+
+fn print_human(report: &Report, fields: &[(String, Option<String>)]) {
+    let label_width = fields.iter().map(|(name, _)| name.len()).max().unwrap_or(0) + 1;
+    for (name, value) in fields {
+        // optional fields are only listed when present, the core ones always
+        if name == "AutoUpgrade"
+            || value.is_none()
+                && !["Id", "Name", "Version", "ApiVersion", "Author"].contains(&name.as_str())
+        {
+            continue;
+        }
+        let label = format!("{name}:");
+        println!("{label:<label_width$} {}", value.as_deref().unwrap_or("-"));
+    }
+    let label = "AutoUpgrade:";
+    let auto_upgrade = match report.auto_upgrade {
+        Some(true) => "true (Renoise may update this tool automatically)",
+        Some(false) => "false (updates are installed by hand)",
+        None => "not set, updates are installed by hand",
+    };
+    println!("{label:<label_width$} {auto_upgrade}");
+    println!();
+    if let Some(comment) = &report.comment {
+        println!("Build:");
+        for line in comment.lines() {
+            println!("  {line}");
+        }
+        println!();
+    }
+
+    let width = report.files.iter().map(|f| f.name.len()).max().unwrap_or(0);
+    for file in &report.files {
+        println!(
+            "  {:<width$}  {:>10}  {:>10}",
+            file.name, file.size, file.compressed_size
+        );
+    }
+    println!();
+    println!(
+        "{} file(s), {} bytes uncompressed, {} bytes compressed",
+        report.files.len(),
+        report.uncompressed_size,
+        report.compressed_size
+    );
+}
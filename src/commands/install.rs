@@ -0,0 +1,31 @@
+use crate::archive::extract;
+use crate::cli::{GlobalArgs, InstallArgs};
+use crate::commands::package;
+use crate::renoise;
+use std::fs;
+
+pub fn run(global: &GlobalArgs, args: &InstallArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let (artifact, archive_path) = package::built_archive(
+        global,
+        &global.settings()?,
+        args.channel.as_deref(),
+        args.dev,
+    )?;
+
+    let tools_dir = match &args.tools_dir {
+        Some(dir) => dir.clone(),
+        None => renoise::tools_dir(args.renoise_version.as_deref())
+            .ok_or("could not locate the Renoise Scripts/Tools directory, pass --tools-dir")?,
+    };
+
+    let target = tools_dir.join(format!("{}.xrnx", artifact.id));
+    if target.exists() {
+        if global.verbose {
+            println!("Replacing {}", target.display());
+        }
+        fs::remove_dir_all(&target)?;
+    }
+    extract(&archive_path, &target, global.verbose)?;
+    println!("Installed {}", target.display());
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use crate::cli::{GlobalArgs, ManifestAction, ManifestArgs};
+use crate::manifest::{get_field, read_file, set_field, write_file};
+use crate::renoise::ApiVersion;
+use crate::schema::{self, Value};
+use crate::version::ToolVersion;
+
+pub fn run(global: &GlobalArgs, args: &ManifestArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let (contents, encoding) = read_file(&global.manifest)
+        .map_err(|e| format!("cannot read {}: {e}", global.manifest.display()))?;
+
+    let (field, value) = match &args.action {
+        ManifestAction::Get { field } => {
+            check_name(field)?;
+            match get_field(&contents, field)? {
+                Some(value) => println!("{value}"),
+                None => return Err(format!("{field} is not set").into()),
+            }
+            return Ok(());
+        }
+        ManifestAction::Set { field, value } => {
+            check_value(field, value)?;
+            if schema::field_type(field).is_none() {
+                eprintln!("warning: {}", unknown(field));
+            }
+            (field, Some(value.as_str()))
+        }
+        ManifestAction::Unset { field } => {
+            check_name(field)?;
+            (field, None)
+        }
+    };
+
+    let updated = set_field(&contents, field, value)?;
+    if updated != contents {
+        write_file(&global.manifest, &updated, encoding)?;
+    }
+    if global.verbose {
+        match value {
+            Some(value) => println!("{field} = {value}"),
+            None => println!("Removed {field}"),
+        }
+    }
+    Ok(())
+}
+
+/// Refuses `value` for `field` where packaging would: versions `bump` can't
+/// parse, ApiVersions and booleans that aren't ones. Fields Renoise doesn't
+/// know take any text.
+fn check_value(field: &str, value: &str) -> Result<(), String> {
+    check_name(field)?;
+    match schema::field_type(field) {
+        Some(Value::ApiVersion) => {
+            value.parse::<ApiVersion>()?;
+        }
+        Some(Value::Boolean) if !matches!(value, "true" | "false") => {
+            return Err(format!("{field} must be true or false, got '{value}'"));
+        }
+        _ if field == "Version" => {
+            ToolVersion::parse(value).map_err(|e| format!("invalid version '{value}': {e}"))?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Refuses names that can't be an XML element.
+fn check_name(field: &str) -> Result<(), String> {
+    let mut chars = field.chars();
+    let valid = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || "_-.".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("'{field}' is not a valid element name"))
+    }
+}
+
+fn unknown(field: &str) -> String {
+    let names: Vec<&str> = schema::FIELDS.iter().map(|(name, _)| *name).collect();
+    format!(
+        "{field} is not a manifest field Renoise knows ({}), setting it anyway",
+        names.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_values_like_packaging() {
+        assert!(check_value("Version", "1.2").is_ok());
+        assert!(check_value("Version", "1.2.0-beta.1+abc").is_ok());
+        assert!(check_value("Version", "one").is_err());
+        assert!(check_value("ApiVersion", "6.1").is_ok());
+        assert!(check_value("ApiVersion", "6.x").is_err());
+        assert!(check_value("AutoUpgrade", "yes").is_err());
+        assert!(check_value("Author", "anything <at> all").is_ok());
+        assert!(check_value("X-Build", "anything").is_ok());
+        assert!(check_value("1Field", "x").is_err());
+        assert!(check_value("a b", "x").is_err());
+    }
+}
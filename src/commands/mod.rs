@@ -0,0 +1,17 @@
+pub mod bump;
+pub mod bundle;
+pub mod clean;
+pub mod completions;
+pub mod diff;
+pub mod doctor;
+pub mod init;
+pub mod inspect;
+pub mod install;
+pub mod manifest;
+pub mod package;
+pub mod publish;
+pub mod self_update;
+pub mod unpack;
+pub mod validate;
+pub mod verify;
+pub mod watch;
@@ -0,0 +1,1094 @@
+use crate::archive::{
+    SourceFile, TEMP_PREFIX, archive_manifest, collect_sources, source_hashes, verify, zip_sources,
+};
+use crate::bump::{Bumped, plan_bump, plan_channel_bump, rendered_manifest};
+use crate::changelog::{self, changelog_path};
+use crate::checksum;
+use crate::cli::{GlobalArgs, PackageArgs};
+use crate::config::{Artifact, BannedCalls, ManifestFields, PlatformVariant, Settings};
+use crate::feed::published_versions;
+use crate::git;
+use crate::lint;
+use crate::lock;
+use crate::lua;
+use crate::manifest::{self, ManifestError, parse_manifest};
+use crate::placeholders::BuildInfo;
+use crate::renoise;
+use crate::sign;
+use crate::transform::Pipeline;
+use crate::validate::{api_warnings, license_warning};
+use crate::version::ToolVersion;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub fn run(global: &GlobalArgs, args: &PackageArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut settings = global.settings()?;
+    if args.fast {
+        settings.fast = Some(true);
+    }
+    if args.keep_mtimes || args.reproducible {
+        settings.keep_mtimes = Some(args.keep_mtimes);
+    }
+    if let Some(dir) = &args.output {
+        settings.output = Some(std::env::current_dir()?.join(dir));
+    }
+    let root = global.project_root();
+    // with --json only the report goes to stdout
+    let say = |message: String| {
+        if args.json {
+            eprintln!("{message}");
+        } else {
+            println!("{message}");
+        }
+    };
+
+    if args.channel.is_none()
+        && (args.skip_unchanged || settings.skip_unchanged == Some(true))
+        && let Some(lock) = lock::load(root)?
+        && lock.files == source_hashes(root, &settings, &global.manifest)?
+    {
+        say(format!(
+            "No changes since {}, nothing to package",
+            lock.version
+        ));
+        return Ok(());
+    }
+
+    if !args.allow_missing_entry && !settings.main_lua(root).is_file() {
+        return Err(format!(
+            "no main.lua in {}, Renoise won't load the tool; pass --allow-missing-entry for a library bundle",
+            root.display()
+        )
+        .into());
+    }
+    if let Some(warning) = license_warning(root, &settings) {
+        eprintln!("warning: {warning}");
+    }
+    let lint_warnings = if args.lint || args.deny_warnings || settings.lint == Some(true) {
+        lint(global, root, &settings, args.deny_warnings)?
+    } else {
+        0
+    };
+    let channel = args.channel.as_deref();
+    let bumped = match channel {
+        Some(channel) => {
+            let lock = lock::load(root)?.unwrap_or_default();
+            let last = lock.channels.get(channel).map(String::as_str);
+            plan_channel_bump(global, &settings, &args.bump, channel, last)?
+        }
+        None => plan_bump(global, &settings, &args.bump)?,
+    };
+    let check_tags = if args.check_tags || args.no_check_tags {
+        args.check_tags
+    } else {
+        settings.check_tags.unwrap_or(true)
+    };
+    if check_tags {
+        check_latest_tag(root, &bumped.old_version)?;
+    }
+    if let Some(target) = args
+        .target_renoise
+        .as_ref()
+        .or(settings.target_renoise.as_ref())
+    {
+        let strict = args.strict || settings.strict == Some(true);
+        check_api_version(&bumped.updated, target, strict)?;
+    }
+    let new_version = ToolVersion::parse(&bumped.new_version)?;
+    // dev builds carry build metadata and may repackage the same version
+    let is_release = new_version.version.build.is_empty();
+    let artifact = artifact(root, &bumped.updated, channel, !is_release && !args.release)?;
+    let output_zip = settings.archive_path(root, &artifact);
+    let variant_zips: Vec<PathBuf> = settings
+        .platform
+        .keys()
+        .filter(|_| channel.is_none())
+        .map(|name| {
+            let variant = Artifact {
+                platform: Some(name.clone()),
+                ..artifact.clone()
+            };
+            settings.archive_path(root, &variant)
+        })
+        .collect();
+    if !args.force && !args.backup {
+        for path in std::iter::once(&output_zip).chain(&variant_zips) {
+            check_overwrite(path, &bumped.new_version)?;
+        }
+    }
+    if is_release
+        && !args.force
+        && let Some(last) = last_release(global.project_root(), &output_zip)?
+        && new_version.version.cmp_precedence(&last.version) != Ordering::Greater
+    {
+        return Err(format!(
+            "{new_version} is not newer than the last release {last}, pass --force to package it anyway"
+        )
+        .into());
+    }
+    if is_release
+        && !args.force
+        && new_version.version.pre.is_empty()
+        && settings.require_changelog == Some(true)
+    {
+        check_changelog(root, &new_version)?;
+    }
+    if is_release
+        && !args.force
+        && let Some(url) = &settings.update_feed
+    {
+        check_feed(url, &new_version)?;
+    }
+    if args.bump.plan {
+        say(format!(
+            "{}: {} -> {}",
+            bumped.tool_id, bumped.old_version, bumped.new_version
+        ));
+        for path in std::iter::once(&output_zip).chain(&variant_zips) {
+            say(format!("would create {}", path.display()));
+        }
+        return Ok(());
+    }
+    if args.backup {
+        for path in std::iter::once(&output_zip).chain(&variant_zips) {
+            if let Some(moved) = back_up(path)? {
+                say(format!("Moved {} to {}", path.display(), moved.display()));
+            }
+        }
+    }
+
+    // the manifest (and synced files) are only rewritten once the archive exists,
+    // and restored if anything after that fails; channel builds leave them alone
+    let mut overrides = bumped
+        .synced
+        .iter()
+        .map(|f| (f.path.clone(), f.updated.clone().into_bytes()))
+        .collect();
+    fill_placeholders(root, &settings, &bumped.new_version, &mut overrides)?;
+    let staged = stage(
+        global,
+        &settings,
+        &bumped.encoding.encode(&bumped.updated),
+        &overrides,
+        &[],
+        &output_zip,
+        is_release,
+    )?;
+    let written = if channel.is_some() {
+        finalize(&staged, &output_zip)
+    } else {
+        bumped
+            .write(global)
+            .and_then(|_| finalize(&staged, &output_zip))
+    };
+    if let Err(e) = written {
+        let _ = fs::remove_file(&staged);
+        if channel.is_none() {
+            bumped.restore(global)?;
+        }
+        return Err(e);
+    }
+    if is_release {
+        let mut lock = lock::load(root)?.unwrap_or_default();
+        match channel {
+            Some(channel) => {
+                lock.channels
+                    .insert(channel.to_string(), bumped.new_version.clone());
+            }
+            None => {
+                lock.version = bumped.new_version.clone();
+                lock.files = source_hashes(root, &settings, &global.manifest)?;
+            }
+        }
+        lock::save(root, &lock)?;
+    }
+    checksum::write(&settings, &output_zip)?;
+    say(format!("Created {}", output_zip.display()));
+    let mut created = vec![output_zip.clone()];
+    if args.also_zip {
+        let copy = zip_copy(&settings, &output_zip)?;
+        say(format!("Created {}", copy.display()));
+        created.push(copy);
+    }
+    if args.stats || args.json {
+        print_stats(&output_zip, args.top, args.json)?;
+    }
+    let signing = settings.signing.clone().unwrap_or_default();
+    if args.sign {
+        for path in &created {
+            say(format!(
+                "Signed as {}",
+                sign::sign(&signing, path)?.display()
+            ));
+        }
+    }
+
+    // platform variants follow the main release line only
+    let variants = if channel.is_none() {
+        &settings.platform
+    } else {
+        &BTreeMap::new()
+    };
+    for (name, variant) in variants {
+        let artifact = Artifact {
+            platform: Some(name.clone()),
+            ..artifact.clone()
+        };
+        let path = package_platform(
+            global, &settings, &bumped, &overrides, &artifact, variant, is_release,
+        )?;
+        say(format!("Created {}", path.display()));
+        let mut created = vec![path.clone()];
+        if args.also_zip {
+            let copy = zip_copy(&settings, &path)?;
+            say(format!("Created {}", copy.display()));
+            created.push(copy);
+        }
+        if args.sign {
+            for path in &created {
+                say(format!(
+                    "Signed as {}",
+                    sign::sign(&signing, path)?.display()
+                ));
+            }
+        }
+    }
+    if lint_warnings > 0 {
+        say(format!("{lint_warnings} lint warning(s), see above"));
+    }
+    Ok(())
+}
+
+/// Prints the ApiVersion check's and luacheck's warnings about the packaged
+/// `.lua` files and returns how many there were, failing on any with
+/// `deny_warnings`.
+fn lint(
+    global: &GlobalArgs,
+    root: &Path,
+    settings: &Settings,
+    deny_warnings: bool,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let files: Vec<PathBuf> = collect_sources(root, settings, false)?
+        .into_iter()
+        .filter(|source| source.name.ends_with(".lua"))
+        .map(|source| {
+            source
+                .path
+                .strip_prefix(root)
+                .map(Path::to_path_buf)
+                .unwrap_or(source.path)
+        })
+        .collect();
+    let (contents, _) = manifest::read_file(&global.manifest)?;
+    let mut warnings = match manifest::read_manifest(&contents)?.api_version {
+        Some(api_version) => api_warnings(root, settings, api_version)?,
+        None => Vec::new(),
+    };
+    warnings.extend(lint::luacheck(root, &files)?);
+    for warning in &warnings {
+        eprintln!("warning: {warning}");
+    }
+    if deny_warnings && !warnings.is_empty() {
+        return Err(format!(
+            "{} lint warning(s), not packaging with --deny-warnings",
+            warnings.len()
+        )
+        .into());
+    }
+    Ok(warnings.len())
+}
+
+/// Builds the platform variant named in `artifact` of the release that was
+/// just packaged.
+fn package_platform(
+    global: &GlobalArgs,
+    settings: &Settings,
+    bumped: &Bumped,
+    overrides: &BTreeMap<PathBuf, Vec<u8>>,
+    artifact: &Artifact,
+    variant: &PlatformVariant,
+    release: bool,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let root = global.project_root();
+    let name = artifact.platform.as_deref().unwrap_or_default();
+    let mut fields = variant
+        .manifest
+        .as_ref()
+        .map(ManifestFields::elements)
+        .unwrap_or_default();
+    fields.push(("Platform", name.to_string()));
+    let manifest = manifest::render(Some(&bumped.updated), &fields)?;
+
+    let mut extra = Vec::new();
+    for file in &variant.files {
+        let path = root.join(file);
+        if !path.is_file() {
+            return Err(format!("{} (platform {name}) not found", path.display()).into());
+        }
+        extra.push(SourceFile {
+            name: entry_name(file),
+            path,
+        });
+    }
+
+    let output_zip = settings.archive_path(root, artifact);
+    let staged = stage(
+        global,
+        settings,
+        &bumped.encoding.encode(&manifest),
+        overrides,
+        &extra,
+        &output_zip,
+        release,
+    )?;
+    if let Err(e) = finalize(&staged, &output_zip) {
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
+    checksum::write(settings, &output_zip)?;
+    Ok(output_zip)
+}
+
+/// Refuses to replace `path` if it is an archive of the same `version`.
+fn check_overwrite(path: &Path, version: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let mut zip = zip::ZipArchive::new(fs::File::open(path)?)?;
+    let (_, existing) = parse_manifest(&archive_manifest(&mut zip, path)?)?;
+    if existing == version {
+        return Err(format!(
+            "{} already holds {version}, pass --force to overwrite it or --backup to keep a copy",
+            path.display()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Moves an existing archive at `path` aside, to a name carrying its
+/// modification time, e.g. `<id>-20260102T030405Z.xrnx`.
+fn back_up(path: &Path) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let at = time::OffsetDateTime::from(fs::metadata(path)?.modified()?);
+    let stamp = format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        at.year(),
+        u8::from(at.month()),
+        at.day(),
+        at.hour(),
+        at.minute(),
+        at.second()
+    );
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let backup = match path.extension() {
+        Some(extension) => {
+            path.with_file_name(format!("{stem}-{stamp}.{}", extension.to_string_lossy()))
+        }
+        None => path.with_file_name(format!("{stem}-{stamp}")),
+    };
+    fs::rename(path, &backup)?;
+    Ok(Some(backup))
+}
+
+/// Highest version recorded in rnplug.lock or found in the existing archive.
+fn last_release(
+    root: &Path,
+    output_zip: &Path,
+) -> Result<Option<ToolVersion>, Box<dyn std::error::Error>> {
+    let mut versions = Vec::new();
+    if let Some(lock) = lock::load(root)?
+        && !lock.version.is_empty()
+    {
+        versions.push(lock.version);
+    }
+    if output_zip.exists() {
+        let mut zip = zip::ZipArchive::new(fs::File::open(output_zip)?)?;
+        let (_, version) = parse_manifest(&archive_manifest(&mut zip, output_zip)?)?;
+        versions.push(version);
+    }
+
+    let mut last: Option<ToolVersion> = None;
+    for version in versions {
+        let version = ToolVersion::parse(&version)?;
+        if last
+            .as_ref()
+            .is_none_or(|l| version.version.cmp_precedence(&l.version) == Ordering::Greater)
+        {
+            last = Some(version);
+        }
+    }
+    Ok(last)
+}
+
+/// Fails if the manifest `version` is older than the highest `v*` tag, which
+/// usually means the release branch was not merged back.
+fn check_latest_tag(root: &Path, version: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let current = ToolVersion::parse(version)?;
+    let latest = git::tags(root, "v*")
+        .into_iter()
+        .filter_map(|tag| Some((ToolVersion::parse(tag.strip_prefix('v')?).ok()?, tag)))
+        .max_by(|(a, _), (b, _)| a.version.cmp_precedence(&b.version));
+    if let Some((latest, tag)) = latest
+        && current.version.cmp_precedence(&latest.version) == Ordering::Less
+    {
+        return Err(format!(
+            "manifest version {current} is behind tag {tag}, merge the release first or pass --no-check-tags"
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Compares the manifest's ApiVersion with the one of the `target` Renoise
+/// version, warning about a mismatch or failing in `strict` mode.
+fn check_api_version(
+    manifest: &str,
+    target: &str,
+    strict: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let expected = renoise::api_version(target)
+        .ok_or_else(|| format!("unknown Renoise version '{target}'"))?;
+    let Some(declared) = manifest::read_manifest(manifest)?.api_version else {
+        return Err("manifest has no ApiVersion to check against --target-renoise".into());
+    };
+    if declared == expected {
+        return Ok(());
+    }
+    let problem = format!(
+        "manifest ApiVersion {declared} does not match Renoise {target}, which uses ApiVersion {expected}"
+    );
+    if strict {
+        return Err(problem.into());
+    }
+    eprintln!("warning: {problem}");
+    Ok(())
+}
+
+/// Fails if CHANGELOG.md has no entry for `version`.
+fn check_changelog(root: &Path, version: &ToolVersion) -> Result<(), Box<dyn std::error::Error>> {
+    let path =
+        changelog_path(root).ok_or("require_changelog is set but there is no CHANGELOG.md")?;
+    if !changelog::has_entry(&fs::read_to_string(&path)?, &version.version) {
+        return Err(format!(
+            "{} has no entry for {version}, add the release notes first",
+            path.display()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Fails if `version` is already published at the update feed `url`.
+fn check_feed(url: &str, version: &ToolVersion) -> Result<(), Box<dyn std::error::Error>> {
+    for published in published_versions(url)? {
+        let Ok(published) = ToolVersion::parse(&published) else {
+            continue;
+        };
+        if published.version.cmp_precedence(&version.version) == Ordering::Equal {
+            return Err(format!(
+                "{version} is already published at {url}, pull the latest manifest or pass --force"
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Zips the project as described by the current manifest, without touching its version.
+/// `overrides` replace the contents of files, keyed by their path relative to
+/// the project root, and a `dev` build's archive is named as such.
+pub fn build(
+    global: &GlobalArgs,
+    settings: &Settings,
+    mut overrides: BTreeMap<PathBuf, Vec<u8>>,
+    dev: bool,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let (_, manifest, encoding) = rendered_manifest(global, settings)?;
+    let artifact = artifact(global.project_root(), &manifest, None, dev)?;
+    fill_placeholders(
+        global.project_root(),
+        settings,
+        &artifact.version,
+        &mut overrides,
+    )?;
+
+    let output_zip = settings.archive_path(global.project_root(), &artifact);
+    let staged = stage(
+        global,
+        settings,
+        &encoding.encode(&manifest),
+        &overrides,
+        &[],
+        &output_zip,
+        false,
+    )?;
+    if let Err(e) = finalize(&staged, &output_zip) {
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
+    Ok(output_zip)
+}
+
+/// What [`build`] packages instead of the files on disk, before `overrides`
+/// of its own: the files with placeholders, filled in for the current version.
+pub fn build_overrides(
+    global: &GlobalArgs,
+    settings: &Settings,
+) -> Result<BTreeMap<PathBuf, Vec<u8>>, Box<dyn std::error::Error>> {
+    let (_, manifest, _) = rendered_manifest(global, settings)?;
+    let mut overrides = BTreeMap::new();
+    fill_placeholders(
+        global.project_root(),
+        settings,
+        &Artifact::of(&manifest)?.version,
+        &mut overrides,
+    )?;
+    Ok(overrides)
+}
+
+/// What the archive of the tool in `manifest` is named after: its `channel`,
+/// if it is a channel build, and the [`dev_suffix`] if it is a `dev` build.
+pub fn artifact(
+    root: &Path,
+    manifest: &str,
+    channel: Option<&str>,
+    dev: bool,
+) -> Result<Artifact, ManifestError> {
+    Ok(Artifact {
+        channel: channel.map(str::to_string),
+        dev: dev.then(|| dev_suffix(root)),
+        ..Artifact::of(manifest)?
+    })
+}
+
+/// The archive packaged last from the current manifest, or the last build on
+/// `channel` recorded in rnplug.lock, as a `dev` build (by `watch`) if asked.
+/// Versions with build metadata are looked for as dev builds too, then under
+/// the clean name `--release` gives them.
+pub fn built_archive(
+    global: &GlobalArgs,
+    settings: &Settings,
+    channel: Option<&str>,
+    dev: bool,
+) -> Result<(Artifact, PathBuf), Box<dyn std::error::Error>> {
+    let root = global.project_root();
+    let (_, mut manifest, _) = rendered_manifest(global, settings)?;
+    if let Some(channel) = channel {
+        let lock = lock::load(root)?.unwrap_or_default();
+        let version = lock.channels.get(channel).ok_or_else(|| {
+            format!(
+                "no {channel} build in rnplug.lock, run `rnplug package --channel {channel}` first"
+            )
+        })?;
+        manifest = manifest::set_version(&manifest, version)?;
+    }
+    let (_, version) = parse_manifest(&manifest)?;
+    let names: &[bool] = if dev {
+        &[true]
+    } else if !ToolVersion::parse(&version)?.version.build.is_empty() {
+        &[true, false]
+    } else {
+        &[false]
+    };
+    let mut tried = Vec::new();
+    for &dev in names {
+        let artifact = artifact(root, &manifest, channel, dev)?;
+        let path = settings.archive_path(root, &artifact);
+        if path.exists() {
+            return Ok((artifact, path));
+        }
+        tried.push(path);
+    }
+    Err(format!(
+        "{} not found, run `rnplug package` first",
+        tried[0].display()
+    )
+    .into())
+}
+
+/// `dev.<shortsha>` for the name of a dev build's archive, just `dev` outside
+/// a git repository.
+fn dev_suffix(root: &Path) -> String {
+    match git::git(root, &["rev-parse", "--short", "HEAD"]) {
+        Ok(sha) => format!("dev.{sha}"),
+        Err(_) => "dev".to_string(),
+    }
+}
+
+/// Writes the archive to a temporary file next to `output_zip`, removing it
+/// again if zipping fails. `release` builds (no build metadata) get the
+/// release-only transforms.
+fn stage(
+    global: &GlobalArgs,
+    settings: &Settings,
+    manifest: &[u8],
+    overrides: &BTreeMap<PathBuf, Vec<u8>>,
+    extra: &[SourceFile],
+    output_zip: &Path,
+    release: bool,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let transforms = Pipeline::for_build(global.project_root(), settings, overrides, release);
+    if settings.syntax_check != Some(false) || settings.banned_calls.is_some() {
+        let lua = packaged_lua(global.project_root(), settings, &transforms, extra)?;
+        if settings.syntax_check != Some(false) {
+            check_syntax(&lua)?;
+        }
+        if let Some(banned) = &settings.banned_calls {
+            check_banned(banned, &lua)?;
+        }
+    }
+    let dir = output_zip.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+
+    let file_name = output_zip.file_name().unwrap_or_default().to_string_lossy();
+    let staged = dir.join(format!("{TEMP_PREFIX}{file_name}"));
+    if let Err(e) = zip_sources(
+        global.project_root(),
+        settings,
+        manifest,
+        &transforms,
+        extra,
+        &staged,
+        global.verbose,
+    )
+    .and_then(|written| {
+        let main_lua = settings.main_lua(global.project_root()).is_file();
+        verify(&staged, &written, main_lua)
+    })
+    .and_then(|_| check_size(settings, &staged, output_zip))
+    {
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
+    Ok(staged)
+}
+
+const MEGABYTE: f64 = 1024.0 * 1024.0;
+
+/// Entry names and contents of the `.lua` files going into the archive, as
+/// they are after `transforms`.
+fn packaged_lua(
+    root: &Path,
+    settings: &Settings,
+    transforms: &Pipeline,
+    extra: &[SourceFile],
+) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let sources = collect_sources(root, settings, false)?;
+    let mut files = Vec::new();
+    for source in sources.iter().chain(extra) {
+        if !source.name.ends_with(".lua") {
+            continue;
+        }
+        let mut contents = fs::read(&source.path)?;
+        if transforms.applies_to(source) {
+            contents = transforms.apply(source, contents)?;
+        }
+        let code = String::from_utf8_lossy(&contents).into_owned();
+        files.push((source.name.clone(), code));
+    }
+    Ok(files)
+}
+
+/// Fails on syntax errors in the packaged `.lua` files, as they'd only show up
+/// once Renoise loads the tool.
+fn check_syntax(lua: &[(String, String)]) -> Result<(), Box<dyn std::error::Error>> {
+    let errors: Vec<String> = lua
+        .iter()
+        .flat_map(|(name, code)| lua::syntax_errors(name, code))
+        .collect();
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "{} Lua syntax error(s), not packaging:\n  {}",
+        errors.len(),
+        errors.join("\n  ")
+    )
+    .into())
+}
+
+/// Warns about uses of the `[banned_calls]` functions in the packaged `.lua`
+/// files, failing on those listed under `deny`.
+fn check_banned(
+    banned: &BannedCalls,
+    lua: &[(String, String)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut denied = Vec::new();
+    for (name, code) in lua {
+        for warning in lint::banned_calls(name, code, &banned.warn) {
+            eprintln!("warning: {warning}");
+        }
+        denied.extend(lint::banned_calls(name, code, &banned.deny));
+    }
+    if denied.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "{} banned call(s), not packaging:\n  {}",
+        denied.len(),
+        denied.join("\n  ")
+    )
+    .into())
+}
+
+/// Warns about (or, over `max_total`, refuses) archives and files bigger than
+/// the `[size_limits]`, listing the biggest entries.
+fn check_size(
+    settings: &Settings,
+    staged: &Path,
+    output_zip: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let limits = settings.size_limits.clone().unwrap_or_default();
+    let mut entries = entry_sizes(staged)?;
+    if let Some(limit) = limits.warn_file {
+        for EntrySize { name, size, .. } in &entries {
+            if *size as f64 > limit * MEGABYTE {
+                eprintln!(
+                    "warning: {name} is {}, over the {limit} MB file limit",
+                    format_size(*size)
+                );
+            }
+        }
+    }
+
+    let total = fs::metadata(staged)?.len() as f64;
+    let over = |limit: Option<f64>| limit.filter(|limit| total > limit * MEGABYTE);
+    let (limit, fatal) = match (over(limits.max_total), over(limits.warn_total)) {
+        (Some(limit), _) => (limit, true),
+        (None, Some(limit)) => (limit, false),
+        (None, None) => return Ok(()),
+    };
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.compressed_size));
+    let mut message = format!(
+        "{} is {}, over the {limit} MB limit; biggest files:",
+        output_zip.display(),
+        format_size(total as u64)
+    );
+    for entry in entries.iter().take(5) {
+        message.push_str(&format!(
+            "\n  {:>9}  {}",
+            format_size(entry.compressed_size),
+            entry.name
+        ));
+    }
+    if fatal {
+        return Err(message.into());
+    }
+    eprintln!("warning: {message}");
+    Ok(())
+}
+
+#[derive(Clone, Serialize)]
+struct EntrySize {
+    name: String,
+    size: u64,
+    compressed_size: u64,
+}
+
+/// Sizes of the files in `archive`, in archive order.
+fn entry_sizes(archive: &Path) -> Result<Vec<EntrySize>, Box<dyn std::error::Error>> {
+    let mut zip = zip::ZipArchive::new(fs::File::open(archive)?)?;
+    let mut entries = Vec::new();
+    for i in 0..zip.len() {
+        let entry = zip.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        entries.push(EntrySize {
+            name: entry.name().to_string(),
+            size: entry.size(),
+            compressed_size: entry.compressed_size(),
+        });
+    }
+    Ok(entries)
+}
+
+#[derive(Serialize)]
+struct Stats {
+    archive: PathBuf,
+    files: Vec<EntrySize>,
+    size: u64,
+    compressed_size: u64,
+    /// Compressed size over the original size.
+    ratio: f64,
+    /// The `--top` biggest files after compression.
+    largest: Vec<EntrySize>,
+}
+
+/// Prints how well each file of `archive` compressed, and the `top` ones
+/// taking the most space, for `--stats`.
+fn print_stats(archive: &Path, top: usize, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let files = entry_sizes(archive)?;
+    let size: u64 = files.iter().map(|f| f.size).sum();
+    let compressed_size: u64 = files.iter().map(|f| f.compressed_size).sum();
+    let ratio = |size: u64, compressed: u64| {
+        if size == 0 {
+            1.0
+        } else {
+            compressed as f64 / size as f64
+        }
+    };
+    let mut largest = files.clone();
+    largest.sort_by_key(|f| std::cmp::Reverse(f.compressed_size));
+    largest.truncate(top);
+    let stats = Stats {
+        archive: archive.to_path_buf(),
+        ratio: ratio(size, compressed_size),
+        files,
+        size,
+        compressed_size,
+        largest,
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    let width = stats.files.iter().map(|f| f.name.len()).max().unwrap_or(0);
+    for file in &stats.files {
+        println!(
+            "  {:<width$}  {:>9}  {:>9}  {:>4.0}%",
+            file.name,
+            format_size(file.size),
+            format_size(file.compressed_size),
+            ratio(file.size, file.compressed_size) * 100.0
+        );
+    }
+    println!(
+        "{} file(s), {} compressed to {} ({:.0}%)",
+        stats.files.len(),
+        format_size(stats.size),
+        format_size(stats.compressed_size),
+        stats.ratio * 100.0
+    );
+    if !stats.largest.is_empty() {
+        println!("Largest after compression:");
+        for file in &stats.largest {
+            println!("  {:>9}  {}", format_size(file.compressed_size), file.name);
+        }
+    }
+    Ok(())
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let bytes = bytes as f64;
+    if bytes < MEGABYTE {
+        format!("{:.0} KB", bytes / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes / MEGABYTE)
+    }
+}
+
+/// Copies the finished `archive` to a `.zip` next to it for `--also-zip`, the
+/// same bytes under the name some distribution channels want.
+fn zip_copy(settings: &Settings, archive: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let copy = archive.with_extension("zip");
+    let file_name = copy.file_name().unwrap_or_default().to_string_lossy();
+    let staged = copy.with_file_name(format!("{TEMP_PREFIX}{file_name}"));
+    let copied = fs::copy(archive, &staged)
+        .map_err(Into::into)
+        .and_then(|_| finalize(&staged, &copy));
+    if let Err(e) = copied {
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
+    checksum::write(settings, &copy)?;
+    Ok(copy)
+}
+
+/// Moves the finished archive into place, replacing any old one in a single
+/// rename. Across filesystems, where that fails, the archive is first copied
+/// next to `output_zip` and synced, so a half-written copy never takes its name.
+fn finalize(staged: &Path, output_zip: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    finalize_with(staged, output_zip, |from, to| fs::rename(from, to))
+}
+
+/// [`finalize`], moving `staged` with `rename`.
+fn finalize_with(
+    staged: &Path,
+    output_zip: &Path,
+    rename: impl Fn(&Path, &Path) -> io::Result<()>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::File::options().write(true).open(staged)?.sync_all()?;
+    match rename(staged, output_zip) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            let dir = output_zip.parent().unwrap_or(Path::new("."));
+            let file_name = output_zip.file_name().unwrap_or_default().to_string_lossy();
+            let copy = dir.join(format!("{TEMP_PREFIX}copy-{file_name}"));
+            let copied = fs::copy(staged, &copy)
+                .and_then(|_| fs::File::options().write(true).open(&copy)?.sync_all())
+                .and_then(|_| fs::rename(&copy, output_zip));
+            if copied.is_err() {
+                let _ = fs::remove_file(&copy);
+            }
+            copied?;
+            fs::remove_file(staged)?;
+        }
+        renamed => renamed?,
+    }
+    Ok(())
+}
+
+/// Adds the `placeholders` files with their placeholders filled in to
+/// `overrides`, on top of any version sync already applied to them.
+fn fill_placeholders(
+    root: &Path,
+    settings: &Settings,
+    version: &str,
+    overrides: &mut BTreeMap<PathBuf, Vec<u8>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if settings.placeholders.is_empty() {
+        return Ok(());
+    }
+    let info = BuildInfo::current(root, version);
+    for file in &settings.placeholders {
+        let contents = match overrides.remove(file) {
+            Some(contents) => contents,
+            None => fs::read(root.join(file))
+                .map_err(|e| format!("cannot read {}: {e}", file.display()))?,
+        };
+        let text = String::from_utf8(contents).map_err(|_| {
+            format!(
+                "{} is not UTF-8, placeholders can't be filled in",
+                file.display()
+            )
+        })?;
+        overrides.insert(file.clone(), info.apply(&text).into_bytes());
+    }
+    Ok(())
+}
+
+/// Archive entry name for a path relative to the project root.
+fn entry_name(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    const MANIFEST: &str = "<?xml version=\"1.0\"?>\n<RenoiseScriptingTool doc_version=\"0\">\n  <ApiVersion>6</ApiVersion>\n  <Id>com.example.Tool</Id>\n  <Version>1.0</Version>\n</RenoiseScriptingTool>\n";
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rnplug-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn finalize_copies_across_devices() {
+        let dir = scratch_dir("finalize");
+        let staged = dir.join(format!("{TEMP_PREFIX}tool.xrnx"));
+        let output = dir.join("tool.xrnx");
+        fs::write(&staged, b"new archive").unwrap();
+        fs::write(&output, b"old archive").unwrap();
+
+        let cross_device = |_: &Path, _: &Path| Err(io::Error::from(io::ErrorKind::CrossesDevices));
+        finalize_with(&staged, &output, cross_device).unwrap();
+
+        assert_eq!(fs::read(&output).unwrap(), b"new archive");
+        assert!(!staged.exists());
+        let mut left: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["tool.xrnx"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Parses `rnplug <args>` run on the project in `dir`.
+    fn cli(dir: &Path, args: &[&str]) -> crate::cli::Cli {
+        let manifest = dir.join("manifest.xml");
+        crate::cli::Cli::try_parse_from(
+            ["rnplug", "--manifest", manifest.to_str().unwrap()]
+                .iter()
+                .chain(args),
+        )
+        .unwrap()
+    }
+
+    /// Runs `rnplug package` with `args` on the project in `dir`.
+    fn package(dir: &Path, args: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+        let cli = cli(dir, &[&["package"], args].concat());
+        let Some(crate::cli::Command::Package(package)) = &cli.command else {
+            unreachable!();
+        };
+        run(&cli.global, package)
+    }
+
+    #[test]
+    fn writes_generated_fields_only_with_the_release() {
+        let dir = scratch_dir("render");
+        let manifest = MANIFEST;
+        fs::write(dir.join("manifest.xml"), manifest).unwrap();
+        fs::write(dir.join("rnplug.toml"), "[manifest]\nauthor = \"Jo\"\n").unwrap();
+        fs::write(dir.join("main.lua"), "local x = = 1\n").unwrap();
+
+        assert!(package(&dir, &["--no-check-tags"]).is_err());
+        assert_eq!(
+            fs::read_to_string(dir.join("manifest.xml")).unwrap(),
+            manifest
+        );
+
+        fs::write(dir.join("main.lua"), "print('hi')\n").unwrap();
+        package(&dir, &["--no-check-tags", "--channel", "beta"]).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("manifest.xml")).unwrap(),
+            manifest
+        );
+        let archive = dir.join("release/beta/com.example.Tool-beta.xrnx");
+        let mut zip = zip::ZipArchive::new(fs::File::open(&archive).unwrap()).unwrap();
+        let packaged = archive_manifest(&mut zip, &archive).unwrap();
+        assert!(packaged.contains("<Author>Jo</Author>"), "{packaged}");
+
+        package(&dir, &["--no-check-tags"]).unwrap();
+        let written = fs::read_to_string(dir.join("manifest.xml")).unwrap();
+        assert!(written.contains("<Author>Jo</Author>"), "{written}");
+        assert!(written.contains("<Version>1.1</Version>"), "{written}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn finds_channel_and_dev_builds() {
+        let dir = scratch_dir("built");
+        fs::write(dir.join("manifest.xml"), MANIFEST).unwrap();
+        fs::write(
+            dir.join("rnplug.toml"),
+            "artifact_name = \"{id}-{version}.xrnx\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("main.lua"), "print('hi')\n").unwrap();
+        let global = cli(&dir, &[]).global;
+        let settings = global.settings().unwrap();
+        assert!(built_archive(&global, &settings, None, false).is_err());
+
+        package(&dir, &["--no-check-tags", "--channel", "beta"]).unwrap();
+        let (artifact, path) = built_archive(&global, &settings, Some("beta"), false).unwrap();
+        assert_eq!(artifact.version, "1.1-beta.1");
+        assert_eq!(
+            path,
+            dir.join("release/beta/com.example.Tool-1.1-beta.1-beta.xrnx")
+        );
+
+        let built = build(&global, &settings, BTreeMap::new(), true).unwrap();
+        assert_eq!(
+            built_archive(&global, &settings, None, true).unwrap().1,
+            built
+        );
+        assert!(built_archive(&global, &settings, None, false).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
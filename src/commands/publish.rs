@@ -0,0 +1,70 @@
+use crate::cli::{GlobalArgs, PublishArgs};
+use crate::commands::package;
+use crate::git;
+use crate::github::{self, Release};
+use crate::version::parse_version;
+use std::env;
+use std::fs;
+
+pub fn run(global: &GlobalArgs, args: &PublishArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if !args.github {
+        return Err("no publish target given, pass --github".into());
+    }
+
+    let root = global.project_root();
+    let (artifact, asset_path) =
+        package::built_archive(global, &global.settings()?, args.channel.as_deref(), false)?;
+    let (tool_id, version) = (artifact.id.clone(), artifact.version.clone());
+    let prerelease = !parse_version(&version)?.pre.is_empty();
+    let asset_name = asset_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+
+    let token = env::var("GITHUB_TOKEN")
+        .or_else(|_| env::var("GH_TOKEN"))
+        .map_err(|_| "set GITHUB_TOKEN (or GH_TOKEN) to publish")?;
+    let repo = match &args.repo {
+        Some(r) => r.clone(),
+        None => git::github_repo(root)?,
+    };
+
+    let tag = format!("v{version}");
+    if git::tag_exists(root, &tag) {
+        println!("Tag {tag} already exists");
+    } else {
+        git::git(
+            root,
+            &["tag", "-a", &tag, "-m", &format!("{tool_id} {version}")],
+        )?;
+        println!("Tagged {tag}");
+    }
+    git::git(root, &["push", "origin", &format!("refs/tags/{tag}")])?;
+
+    let auth = format!("Bearer {token}");
+    let release: Release = ureq::post(&format!("{}/repos/{repo}/releases", github::API))
+        .set("Authorization", &auth)
+        .set("Accept", "application/vnd.github+json")
+        .send_json(serde_json::json!({
+            "tag_name": tag,
+            "name": format!("{tool_id} {version}"),
+            "draft": args.draft,
+            "prerelease": prerelease,
+        }))?
+        .into_json()?;
+    if global.verbose {
+        println!("Created release {}", release.html_url);
+    }
+
+    // upload_url is a URI template like ".../assets{?name,label}"
+    let upload_url = release.upload_url.split('{').next().unwrap_or_default();
+    ureq::post(upload_url)
+        .query("name", &asset_name)
+        .set("Authorization", &auth)
+        .set("Content-Type", "application/octet-stream")
+        .send_bytes(&fs::read(&asset_path)?)?;
+
+    println!("Published {asset_name} to {}", release.html_url);
+    Ok(())
+}
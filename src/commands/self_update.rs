@@ -0,0 +1,82 @@
+use crate::cli::{GlobalArgs, SelfUpdateArgs};
+use crate::github;
+use semver::Version;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::io::Read;
+
+/// Repository the standalone binaries are released from.
+const REPO: &str = "PORTALSURFER/rls_rnplug";
+
+pub fn run(global: &GlobalArgs, args: &SelfUpdateArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let current = Version::parse(env!("CARGO_PKG_VERSION"))?;
+    let release = github::latest_release(REPO)?;
+    let latest = Version::parse(release.tag_name.trim_start_matches('v'))?;
+
+    if latest <= current {
+        println!("rnplug {current} is up to date");
+        return Ok(());
+    }
+    if args.check {
+        println!("rnplug {latest} is available (installed: {current})");
+        return Ok(());
+    }
+
+    let asset_name = format!(
+        "rnplug-{}-{}{}",
+        env::consts::OS,
+        env::consts::ARCH,
+        env::consts::EXE_SUFFIX
+    );
+    let find = |name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|a| a.name == name)
+            .ok_or_else(|| format!("release {} has no asset {name}", release.tag_name))
+    };
+    let binary = find(&asset_name)?;
+    let checksum = find(&format!("{asset_name}.sha256"))?;
+
+    if global.verbose {
+        println!("Downloading {}", binary.browser_download_url);
+    }
+    let mut bytes = Vec::new();
+    ureq::get(&binary.browser_download_url)
+        .call()?
+        .into_reader()
+        .read_to_end(&mut bytes)?;
+    let expected = ureq::get(&checksum.browser_download_url)
+        .call()?
+        .into_string()?;
+    // checksum files are `<hex>  <file name>`
+    let expected = expected.split_whitespace().next().unwrap_or_default();
+    let actual = format!("{:x}", Sha256::digest(&bytes));
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(format!(
+            "checksum mismatch for {asset_name}: expected {expected}, got {actual}"
+        )
+        .into());
+    }
+
+    let exe = env::current_exe()?;
+    let staged = exe.with_extension("new");
+    let backup = exe.with_extension("old");
+    fs::write(&staged, &bytes)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+    // a running executable can be renamed but not overwritten on Windows
+    fs::rename(&exe, &backup)?;
+    if let Err(e) = fs::rename(&staged, &exe) {
+        fs::rename(&backup, &exe)?;
+        return Err(e.into());
+    }
+    let _ = fs::remove_file(&backup);
+
+    println!("Updated rnplug {current} -> {latest}");
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use crate::archive::extract;
+use crate::cli::{GlobalArgs, UnpackArgs};
+use std::fs;
+use std::path::PathBuf;
+
+pub fn run(global: &GlobalArgs, args: &UnpackArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let dest = match &args.dest {
+        Some(d) => d.clone(),
+        None => {
+            let stem = args.archive.file_stem().unwrap_or_default();
+            PathBuf::from(stem)
+        }
+    };
+
+    if dest.exists() && fs::read_dir(&dest)?.next().is_some() {
+        return Err(format!("{} already exists and is not empty", dest.display()).into());
+    }
+
+    extract(&args.archive, &dest, global.verbose)?;
+    println!(
+        "Unpacked {} into {}",
+        args.archive.display(),
+        dest.display()
+    );
+    Ok(())
+}
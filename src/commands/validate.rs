@@ -0,0 +1,45 @@
+use crate::cli::GlobalArgs;
+use crate::manifest::{read_file, read_manifest};
+use crate::validate::{api_warnings, field_warnings, license_warning, naming_warnings, validate};
+
+pub fn run(global: &GlobalArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let root = global.project_root();
+    let settings = global.settings()?;
+    let problems = validate(&global.manifest, &settings.main_lua(root));
+    if let Ok((contents, _)) = read_file(&global.manifest)
+        && let Ok(manifest) = read_manifest(&contents)
+    {
+        let mut warnings = field_warnings(&contents);
+        if let Some(tool_id) = &manifest.id {
+            let release_dir = settings.release_dir(root);
+            // archives named by a custom `artifact_name` needn't start with the Id
+            let release_dir =
+                Some(release_dir.as_path()).filter(|_| settings.artifact_name.is_none());
+            warnings.extend(naming_warnings(root, tool_id, release_dir));
+        }
+        warnings.extend(license_warning(root, &settings));
+        if let Some(api_version) = manifest.api_version {
+            warnings.extend(api_warnings(root, &settings, api_version)?);
+        }
+        for warning in warnings {
+            eprintln!("warning: {warning}");
+        }
+    }
+    if problems.is_empty() {
+        println!("{} is valid", global.manifest.display());
+        if global.verbose {
+            let manifest = read_manifest(&read_file(&global.manifest)?.0)?;
+            for (name, value) in manifest.all_fields() {
+                if let Some(value) = value {
+                    println!("  {name}: {value}");
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    for problem in &problems {
+        eprintln!("error: {problem}");
+    }
+    Err(format!("{} problem(s) found", problems.len()).into())
+}
@@ -0,0 +1,13 @@
+use crate::cli::{GlobalArgs, VerifyArgs};
+use crate::sign;
+
+pub fn run(global: &GlobalArgs, args: &VerifyArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let signing = global.settings()?.signing.unwrap_or_default();
+    let signature = sign::verify(&signing, &args.archive)?;
+    println!(
+        "{} matches its signature {}",
+        args.archive.display(),
+        signature.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use crate::cli::{GlobalArgs, WatchArgs};
+use crate::commands::package;
+use crate::config::{CONFIG_FILE, ConfigError, Settings};
+use notify::{Event, RecursiveMode, Watcher};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+pub fn run(global: &GlobalArgs, args: &WatchArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let root = global.project_root().canonicalize()?;
+    let mut settings = load(global, args)?;
+    let config = root.join(CONFIG_FILE);
+    let manifest_name = global.manifest.file_name().unwrap_or_default().to_owned();
+
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+
+    println!("Watching {} (Ctrl-C to stop)", root.display());
+    let mut build_no = 1;
+    build(global, &settings, build_no);
+
+    let debounce = Duration::from_millis(args.debounce);
+    loop {
+        let event = match rx.recv()? {
+            Ok(event) => event,
+            Err(e) => {
+                eprintln!("warning: watching failed: {e}");
+                continue;
+            }
+        };
+        let changed = event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove();
+        let mut reload = event.paths.contains(&config);
+        let relevant = reload
+            || event
+                .paths
+                .iter()
+                .any(|p| is_relevant(&settings, &root, &manifest_name, p));
+        if !changed || !relevant {
+            continue;
+        }
+        // editors tend to save in several steps, wait for things to settle
+        while let Ok(event) = rx.recv_timeout(debounce) {
+            match event {
+                Ok(event) => reload |= event.paths.contains(&config),
+                Err(e) => eprintln!("warning: watching failed: {e}"),
+            }
+        }
+
+        if reload {
+            // keep building with the old settings until the file is fixed
+            match load(global, args) {
+                Ok(reloaded) => {
+                    settings = reloaded;
+                    println!("Reloaded {CONFIG_FILE}");
+                }
+                Err(e) => eprintln!("warning: keeping the previous settings, {e}"),
+            }
+        }
+        build_no += 1;
+        build(global, &settings, build_no);
+    }
+}
+
+/// The project settings with the `watch` flags applied.
+fn load(global: &GlobalArgs, args: &WatchArgs) -> Result<Settings, ConfigError> {
+    let mut settings = global.settings()?;
+    if args.fast {
+        settings.fast = Some(true);
+    }
+    Ok(settings)
+}
+
+/// Whether a change to `path` calls for a rebuild: Lua sources, the manifest
+/// and assets, outside the release directory.
+fn is_relevant(settings: &Settings, root: &Path, manifest_name: &OsStr, path: &Path) -> bool {
+    !path.starts_with(settings.release_dir(root))
+        && (path.extension().is_some_and(|e| e == "lua")
+            || path.file_name() == Some(manifest_name)
+            || path.strip_prefix(root).is_ok_and(|relative| {
+                settings
+                    .assets
+                    .as_ref()
+                    .is_some_and(|assets| assets.matches(relative))
+            }))
+}
+
+fn build(global: &GlobalArgs, settings: &Settings, build_no: u32) {
+    let started = Instant::now();
+    match package::build(global, settings, BTreeMap::new(), true) {
+        Ok(path) => println!(
+            "[build #{build_no}] ok in {} ms -> {}",
+            started.elapsed().as_millis(),
+            path.display()
+        ),
+        Err(e) => eprintln!("[build #{build_no}] failed: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Assets;
+    use std::path::PathBuf;
+
+    #[test]
+    fn rebuilds_for_sources_manifest_and_assets() {
+        let root = Path::new("/project");
+        let settings = Settings {
+            assets: Some(Assets {
+                extensions: vec!["png".to_string()],
+                dirs: vec![PathBuf::from("samples")],
+            }),
+            ..Settings::default()
+        };
+        let manifest = OsStr::new("manifest.xml");
+        for (path, expected) in [
+            ("/project/main.lua", true),
+            ("/project/lib/util.lua", true),
+            ("/project/manifest.xml", true),
+            ("/project/icons/logo.png", true),
+            ("/project/samples/kick.wav", true),
+            ("/project/notes.txt", false),
+            ("/project/release/old.lua", false),
+        ] {
+            assert_eq!(
+                is_relevant(&settings, root, manifest, Path::new(path)),
+                expected,
+                "{path}"
+            );
+        }
+    }
+}
@@ -0,0 +1,686 @@
+use crate::annotations;
+use crate::archive::readme_path;
+use crate::manifest::{ManifestError, parse_manifest, read_manifest};
+use crate::readme;
+use crate::renoise::{self, ApiVersion};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Optional per-project configuration, read from the project root.
+pub const CONFIG_FILE: &str = "rnplug.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    #[serde(flatten)]
+    pub settings: Settings,
+
+    /// Named overrides of the top-level settings, selected with `--profile`.
+    pub profile: BTreeMap<String, Settings>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Directory archives are written to, relative to the project root.
+    pub output: Option<PathBuf>,
+
+    /// Archive file name with `{id}`, `{name}`, `{version}`, `{channel}` and
+    /// `{platform}` filled in, `{id}.xrnx` unless set. Channel builds and
+    /// platform variants get `-<channel>` or `-<platform>` appended to a name
+    /// that doesn't mention them.
+    pub artifact_name: Option<String>,
+
+    /// How the version changes when no bump flag is given on the command line.
+    pub bump: Option<VersionStrategy>,
+
+    /// Versioning scheme, `semver` unless set.
+    pub scheme: Option<VersionScheme>,
+
+    /// Pre-release channels in promotion order, `alpha`, `beta`, `rc` unless set.
+    pub channels: Option<Vec<String>>,
+
+    /// Don't reset lower components when bumping, like `--keep-patch`.
+    pub keep_patch: Option<bool>,
+
+    /// How many components the bumped version is written with.
+    pub version_format: Option<VersionFormat>,
+
+    /// Fail packaging when the manifest version is behind the highest `v*` tag, on unless set.
+    pub check_tags: Option<bool>,
+
+    /// Renoise version packages are built for, like `--target-renoise`.
+    pub target_renoise: Option<String>,
+
+    /// Fail instead of warning on compatibility problems, like `--strict`.
+    pub strict: Option<bool>,
+
+    /// Don't bump or package when no file changed since the last release.
+    pub skip_unchanged: Option<bool>,
+
+    /// URL of the published manifest or version list, checked before packaging.
+    pub update_feed: Option<String>,
+
+    /// Files that repeat the version and are rewritten on every bump.
+    pub version_sync: Vec<VersionSync>,
+
+    /// Files, relative to the project root, whose `{{VERSION}}`, `{{BUILD_DATE}}`
+    /// and `{{GIT_SHA}}` placeholders are filled in when packaging. The files on
+    /// disk keep the placeholders.
+    pub placeholders: Vec<PathBuf>,
+
+    /// Archive path of a generated Lua module, e.g. `_build.lua`, returning the
+    /// build's `version`, `build_date`, `git_sha` and `channel` so the tool can
+    /// show them without edits on every release.
+    pub build_constants: Option<String>,
+
+    /// Files, relative to the project root, never packaged. `rnplug bundle` adds
+    /// the modules it inlined.
+    pub exclude: Vec<PathBuf>,
+
+    /// Manifest fields kept in rnplug.toml and written into manifest.xml when packaging.
+    pub manifest: Option<ManifestFields>,
+
+    /// Take manifest fields from `-- @tag` comments at the top of main.lua
+    /// (the version stays with `bump`).
+    pub lua_annotations: Option<bool>,
+
+    /// Write the README's description section (or first paragraph) into `<Description>`.
+    pub description_from_readme: Option<bool>,
+
+    /// Leave out files matched by `.gitignore`, on unless set.
+    pub gitignore: Option<bool>,
+
+    /// Package the LICENSE, LICENSE.md or COPYING file, on unless set.
+    pub license: Option<bool>,
+
+    /// Fail packaging a release whose version has no heading in CHANGELOG.md.
+    pub require_changelog: Option<bool>,
+
+    /// The tool is shared with others, so it should come with a license file.
+    pub public: Option<bool>,
+
+    /// Package `*_spec.lua`, `*_test.lua` and `spec/`, `tests/` folders too.
+    pub include_tests: Option<bool>,
+
+    /// Checksum files written next to every archive, `["sha256"]` unless set.
+    pub checksums: Option<Vec<Checksum>>,
+
+    /// Keep a SHA256SUMS file listing every archive in the release directory.
+    pub checksum_list: Option<bool>,
+
+    /// Key used by `package --sign` and `verify`, see [`Signing`].
+    pub signing: Option<Signing>,
+
+    /// Keep each file's modification time in the archive, like `--keep-mtimes`,
+    /// instead of the fixed timestamp that makes builds reproducible.
+    pub keep_mtimes: Option<bool>,
+
+    /// Run luacheck and the ApiVersion check over the packaged `.lua` files,
+    /// like `--lint`.
+    pub lint: Option<bool>,
+
+    /// Check the packaged `.lua` files for syntax errors, on unless set.
+    pub syntax_check: Option<bool>,
+
+    /// Store files without compressing them, like `--fast`, for quicker dev builds.
+    pub fast: Option<bool>,
+
+    /// Add `files.json` with the size and SHA-256 of every packaged file, on unless set.
+    pub file_list: Option<bool>,
+
+    /// Archive and file size limits, see [`SizeLimits`].
+    pub size_limits: Option<SizeLimits>,
+
+    /// Lua functions the packaged sources mustn't use, see [`BannedCalls`].
+    pub banned_calls: Option<BannedCalls>,
+
+    /// What to do with symlinks in the project tree, `follow` unless set.
+    pub symlinks: Option<SymlinkPolicy>,
+
+    /// `[paths]`: project directories and the archive folders their files go
+    /// to, e.g. `src = ""` puts `src/main.lua` at the archive root and
+    /// `res = "assets"` packages `res/**` under `assets/`. Only the names change,
+    /// which files are packaged is decided as usual.
+    pub paths: BTreeMap<PathBuf, PathBuf>,
+
+    /// Changes made to files on their way into the archive, see [`Transforms`].
+    pub transforms: Option<Transforms>,
+
+    /// Non-Lua files to package, see [`Assets`].
+    pub assets: Option<Assets>,
+
+    /// `[platform.<name>]` variants, each packaged as `<id>-<name>.xrnx` next to
+    /// the main archive.
+    pub platform: BTreeMap<String, PlatformVariant>,
+}
+
+/// Manifest overrides and extra files for one platform. The variant's manifest
+/// also gets `<Platform>` set to the section name.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct PlatformVariant {
+    pub manifest: Option<ManifestFields>,
+    /// Files bundled only for this platform, relative to the project root.
+    pub files: Vec<PathBuf>,
+}
+
+/// The `[assets]` table: files packaged besides `.lua` sources and the README.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Assets {
+    /// File extensions packaged wherever they are in the tree, e.g. `["png", "wav"]`.
+    pub extensions: Vec<String>,
+    /// Directories, relative to the project root, packaged with everything in them.
+    pub dirs: Vec<PathBuf>,
+}
+
+impl Assets {
+    /// Whether the file at `relative` (to the project root) is an asset.
+    pub fn matches(&self, relative: &Path) -> bool {
+        let extension = relative.extension().and_then(|e| e.to_str());
+        self.dirs.iter().any(|dir| relative.starts_with(dir))
+            || extension.is_some_and(|extension| {
+                self.extensions
+                    .iter()
+                    .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(extension))
+            })
+    }
+}
+
+/// The `[transforms]` table.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Transforms {
+    /// Leave `--[[DEBUG]]` ... `--[[/DEBUG]]` blocks out of release builds.
+    pub strip_debug: Option<bool>,
+    /// Strip comments and extra whitespace from Lua files in release builds.
+    pub minify: Option<bool>,
+    /// Line endings of packaged text files, as they are unless set.
+    pub line_endings: Option<LineEnding>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+/// The `[size_limits]` table, in megabytes.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SizeLimits {
+    /// Warn when the archive is bigger than this.
+    pub warn_total: Option<f64>,
+    /// Fail when the archive is bigger than this.
+    pub max_total: Option<f64>,
+    /// Warn about every file bigger than this (uncompressed).
+    pub warn_file: Option<f64>,
+}
+
+/// The `[banned_calls]` table: functions such as `os.execute`, `io.popen` or
+/// `loadstring` that packaged `.lua` files are checked for. Only uses spelled
+/// out like that are found, not ones through a local alias.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct BannedCalls {
+    /// Warn about uses of these.
+    pub warn: Vec<String>,
+    /// Refuse to package sources using these.
+    pub deny: Vec<String>,
+}
+
+impl Default for SizeLimits {
+    fn default() -> Self {
+        SizeLimits {
+            warn_total: Some(5.0),
+            max_total: Some(50.0),
+            warn_file: None,
+        }
+    }
+}
+
+/// The `[manifest]` table. Only the fields given here are written, anything
+/// else in manifest.xml (and the version, which `bump` owns) is left alone.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct ManifestFields {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub author: Option<String>,
+    #[serde(deserialize_with = "renoise::toml_api_version")]
+    pub api_version: Option<ApiVersion>,
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    pub category: Option<String>,
+}
+
+impl ManifestFields {
+    /// Declared fields by manifest element name.
+    pub fn elements(&self) -> Vec<(&'static str, String)> {
+        [
+            ("ApiVersion", self.api_version.map(|v| v.to_string())),
+            ("Id", self.id.clone()),
+            ("Name", self.name.clone()),
+            ("Author", self.author.clone()),
+            ("Category", self.category.clone()),
+            ("Description", self.description.clone()),
+            ("Homepage", self.homepage.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value?)))
+        .collect()
+    }
+}
+
+/// A `[[version_sync]]` rule: the first match of `pattern` in `file` gets its
+/// `{version}` placeholder replaced with the new version.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionSync {
+    pub file: PathBuf,
+    #[serde(deserialize_with = "version_pattern")]
+    pub pattern: String,
+}
+
+/// A `version_sync` pattern, which needs text before `{version}` to anchor it.
+fn version_pattern<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    match pattern.split_once("{version}") {
+        Some(("", _)) => Err(serde::de::Error::custom(format!(
+            "version_sync pattern `{pattern}` needs some text before {{version}}"
+        ))),
+        Some(_) => Ok(pattern),
+        None => Err(serde::de::Error::custom(format!(
+            "version_sync pattern `{pattern}` has no {{version}} placeholder"
+        ))),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionScheme {
+    Semver,
+    /// `YYYY.M.patch`, see [`crate::version::bump_calver`].
+    Calver,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionFormat {
+    /// Keep the style of the current manifest version (the default).
+    Preserve,
+    /// Always `major.minor.patch`, `1.3` becomes `1.3.0`.
+    Full,
+    /// Drop a zero patch, `1.3.0` becomes `1.3`.
+    Short,
+}
+
+/// The `[signing]` table.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Signing {
+    /// `minisign` unless set.
+    pub tool: Option<SigningTool>,
+    /// minisign secret key file or GPG key id, the tool's default key unless set.
+    pub key: Option<String>,
+    /// minisign public key, as a file or the key itself, for `verify`.
+    pub public_key: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SigningTool {
+    #[default]
+    Minisign,
+    Gpg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Checksum {
+    Sha256,
+    Sha512,
+}
+
+impl Checksum {
+    /// Extension of the checksum file, `<archive>.<extension>`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Checksum::Sha256 => "sha256",
+            Checksum::Sha512 => "sha512",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SymlinkPolicy {
+    /// Package what the link points to; links back into a parent are skipped.
+    #[default]
+    Follow,
+    /// Leave links out, with a warning.
+    Skip,
+    /// Refuse to package a tree containing links.
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionStrategy {
+    Major,
+    Minor,
+    Patch,
+    ConventionalCommits,
+    GitTag,
+    /// Leave the manifest version alone.
+    Manual,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    UnknownProfile(String),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "cannot read {CONFIG_FILE}: {e}"),
+            ConfigError::Toml(e) => write!(f, "invalid {CONFIG_FILE}: {e}"),
+            ConfigError::UnknownProfile(name) => {
+                write!(f, "profile `{name}` is not defined in {CONFIG_FILE}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Loads `rnplug.toml` from `root`, falling back to defaults when there is none.
+pub fn load(root: &Path) -> Result<Config, ConfigError> {
+    let path = root.join(CONFIG_FILE);
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+    let contents = fs::read_to_string(path).map_err(ConfigError::Io)?;
+    toml::from_str(&contents).map_err(ConfigError::Toml)
+}
+
+impl Config {
+    /// The top-level settings with the given profile applied on top.
+    pub fn resolve(&self, profile: Option<&str>) -> Result<Settings, ConfigError> {
+        let mut settings = self.settings.clone();
+        if let Some(name) = profile {
+            let overrides = self
+                .profile
+                .get(name)
+                .ok_or_else(|| ConfigError::UnknownProfile(name.to_string()))?;
+            settings.apply(overrides);
+        }
+        Ok(settings)
+    }
+}
+
+impl Settings {
+    fn apply(&mut self, overrides: &Settings) {
+        if overrides.output.is_some() {
+            self.output.clone_from(&overrides.output);
+        }
+        if overrides.artifact_name.is_some() {
+            self.artifact_name.clone_from(&overrides.artifact_name);
+        }
+        if overrides.bump.is_some() {
+            self.bump = overrides.bump;
+        }
+        if overrides.scheme.is_some() {
+            self.scheme = overrides.scheme;
+        }
+        if overrides.channels.is_some() {
+            self.channels.clone_from(&overrides.channels);
+        }
+        if overrides.keep_patch.is_some() {
+            self.keep_patch = overrides.keep_patch;
+        }
+        if overrides.version_format.is_some() {
+            self.version_format = overrides.version_format;
+        }
+        if overrides.update_feed.is_some() {
+            self.update_feed.clone_from(&overrides.update_feed);
+        }
+        if overrides.check_tags.is_some() {
+            self.check_tags = overrides.check_tags;
+        }
+        if overrides.target_renoise.is_some() {
+            self.target_renoise.clone_from(&overrides.target_renoise);
+        }
+        if overrides.strict.is_some() {
+            self.strict = overrides.strict;
+        }
+        if overrides.skip_unchanged.is_some() {
+            self.skip_unchanged = overrides.skip_unchanged;
+        }
+        if overrides.lua_annotations.is_some() {
+            self.lua_annotations = overrides.lua_annotations;
+        }
+        if overrides.description_from_readme.is_some() {
+            self.description_from_readme = overrides.description_from_readme;
+        }
+        if overrides.gitignore.is_some() {
+            self.gitignore = overrides.gitignore;
+        }
+        if overrides.license.is_some() {
+            self.license = overrides.license;
+        }
+        if overrides.require_changelog.is_some() {
+            self.require_changelog = overrides.require_changelog;
+        }
+        if overrides.public.is_some() {
+            self.public = overrides.public;
+        }
+        if overrides.include_tests.is_some() {
+            self.include_tests = overrides.include_tests;
+        }
+        if overrides.checksums.is_some() {
+            self.checksums.clone_from(&overrides.checksums);
+        }
+        if overrides.checksum_list.is_some() {
+            self.checksum_list = overrides.checksum_list;
+        }
+        if overrides.signing.is_some() {
+            self.signing.clone_from(&overrides.signing);
+        }
+        if overrides.keep_mtimes.is_some() {
+            self.keep_mtimes = overrides.keep_mtimes;
+        }
+        if overrides.lint.is_some() {
+            self.lint = overrides.lint;
+        }
+        if overrides.syntax_check.is_some() {
+            self.syntax_check = overrides.syntax_check;
+        }
+        if overrides.fast.is_some() {
+            self.fast = overrides.fast;
+        }
+        if overrides.file_list.is_some() {
+            self.file_list = overrides.file_list;
+        }
+        if overrides.size_limits.is_some() {
+            self.size_limits.clone_from(&overrides.size_limits);
+        }
+        if overrides.build_constants.is_some() {
+            self.build_constants.clone_from(&overrides.build_constants);
+        }
+        if overrides.banned_calls.is_some() {
+            self.banned_calls.clone_from(&overrides.banned_calls);
+        }
+        if overrides.symlinks.is_some() {
+            self.symlinks = overrides.symlinks;
+        }
+        if overrides.transforms.is_some() {
+            self.transforms.clone_from(&overrides.transforms);
+        }
+        if overrides.assets.is_some() {
+            self.assets.clone_from(&overrides.assets);
+        }
+        if !overrides.paths.is_empty() {
+            self.paths.clone_from(&overrides.paths);
+        }
+        if !overrides.platform.is_empty() {
+            self.platform.clone_from(&overrides.platform);
+        }
+        if overrides.manifest.is_some() {
+            self.manifest.clone_from(&overrides.manifest);
+        }
+        if !overrides.placeholders.is_empty() {
+            self.placeholders.clone_from(&overrides.placeholders);
+        }
+        if !overrides.exclude.is_empty() {
+            self.exclude.clone_from(&overrides.exclude);
+        }
+        if !overrides.version_sync.is_empty() {
+            self.version_sync.clone_from(&overrides.version_sync);
+        }
+    }
+
+    /// The channel order `bump --promote` walks through.
+    pub fn channels(&self) -> Vec<String> {
+        match &self.channels {
+            Some(channels) => channels.clone(),
+            None => ["alpha", "beta", "rc"].map(String::from).to_vec(),
+        }
+    }
+
+    /// Checksum files to write next to archives.
+    pub fn checksums(&self) -> Vec<Checksum> {
+        self.checksums
+            .clone()
+            .unwrap_or_else(|| vec![Checksum::Sha256])
+    }
+
+    /// Manifest fields generated from the project: the `[manifest]` table and,
+    /// if enabled, main.lua annotations and the README description.
+    pub fn generated_fields(
+        &self,
+        root: &Path,
+    ) -> Result<Vec<(&'static str, String)>, std::io::Error> {
+        let mut fields = self
+            .manifest
+            .as_ref()
+            .map(ManifestFields::elements)
+            .unwrap_or_default();
+        let main_lua = self.main_lua(root);
+        if self.lua_annotations == Some(true) && main_lua.is_file() {
+            for (name, value) in annotations::parse(&fs::read_to_string(main_lua)?) {
+                if name != "Version" {
+                    fields.retain(|(field, _)| *field != name);
+                    fields.push((name, value));
+                }
+            }
+        }
+        if self.description_from_readme == Some(true)
+            && let Some(path) = readme_path(root)
+            && let Some(description) = readme::description(&fs::read_to_string(path)?)
+        {
+            fields.retain(|(name, _)| *name != "Description");
+            fields.push(("Description", description));
+        }
+        Ok(fields)
+    }
+
+    /// Where `relative` (to the project root) goes in the archive, following the
+    /// longest matching `[paths]` entry.
+    pub fn archive_name(&self, relative: &Path) -> PathBuf {
+        self.paths
+            .iter()
+            .filter_map(|(from, to)| Some((from, to.join(relative.strip_prefix(from).ok()?))))
+            .max_by_key(|(from, _)| from.components().count())
+            .map_or_else(|| relative.to_path_buf(), |(_, name)| name)
+    }
+
+    /// The file packaged as the top-level main.lua.
+    pub fn main_lua(&self, root: &Path) -> PathBuf {
+        self.paths
+            .iter()
+            .filter_map(|(from, to)| {
+                Some(
+                    root.join(from)
+                        .join(Path::new("main.lua").strip_prefix(to).ok()?),
+                )
+            })
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| root.join("main.lua"))
+    }
+
+    pub fn release_dir(&self, root: &Path) -> PathBuf {
+        root.join(self.output.as_deref().unwrap_or(Path::new("release")))
+    }
+
+    /// Where the archive described by `artifact` is written, see `artifact_name`.
+    /// Channel builds go to `<release>/<channel>/`.
+    pub fn archive_path(&self, root: &Path, artifact: &Artifact) -> PathBuf {
+        let template = self.artifact_name.as_deref().unwrap_or("{id}.xrnx");
+        let mut name = template
+            .replace("{id}", &artifact.id)
+            .replace("{name}", artifact.name.as_deref().unwrap_or(&artifact.id))
+            .replace("{version}", &artifact.version);
+        for (placeholder, value) in [
+            ("{channel}", &artifact.channel),
+            ("{platform}", &artifact.platform),
+        ] {
+            name = match value {
+                Some(value) if template.contains(placeholder) => name.replace(placeholder, value),
+                Some(value) => with_suffix(&name, value),
+                None => name.replace(placeholder, ""),
+            };
+        }
+        if let Some(dev) = &artifact.dev {
+            name = with_suffix(&name, dev);
+        }
+        // channel builds get a folder of their own, away from the stable archives
+        match &artifact.channel {
+            Some(channel) => self.release_dir(root).join(channel).join(name),
+            None => self.release_dir(root).join(name),
+        }
+    }
+}
+
+/// `name` with `-<suffix>` inserted before its `.xrnx` extension.
+fn with_suffix(name: &str, suffix: &str) -> String {
+    match name.strip_suffix(".xrnx") {
+        Some(stem) => format!("{stem}-{suffix}.xrnx"),
+        None => format!("{name}-{suffix}"),
+    }
+}
+
+/// What an archive's file name is made of, see `artifact_name`.
+#[derive(Debug, Clone, Default)]
+pub struct Artifact {
+    pub id: String,
+    /// The manifest's Name, the Id stands in when it has none.
+    pub name: Option<String>,
+    pub version: String,
+    /// Pre-release channel of a `--channel` build.
+    pub channel: Option<String>,
+    /// Name of a `[platform.<name>]` variant.
+    pub platform: Option<String>,
+    /// `dev.<shortsha>` of a dev build, appended so test builds don't overwrite
+    /// each other.
+    pub dev: Option<String>,
+}
+
+impl Artifact {
+    /// The archive of the tool described by the manifest `contents`.
+    pub fn of(contents: &str) -> Result<Self, ManifestError> {
+        let (id, version) = parse_manifest(contents)?;
+        Ok(Artifact {
+            id,
+            name: read_manifest(contents).ok().and_then(|m| m.name),
+            version,
+            ..Artifact::default()
+        })
+    }
+}
@@ -0,0 +1,70 @@
+use crate::version::BumpKind;
+
+/// Picks the bump a set of Conventional Commits messages calls for: any breaking
+/// change is major, any `feat` minor, any `fix` patch. `None` when nothing in
+/// them warrants a release.
+pub fn infer_bump<'a>(messages: impl IntoIterator<Item = &'a str>) -> Option<BumpKind> {
+    messages
+        .into_iter()
+        .filter_map(classify)
+        .max_by_key(|kind| match kind {
+            BumpKind::Patch => 0,
+            BumpKind::Minor => 1,
+            BumpKind::Major => 2,
+        })
+}
+
+fn classify(message: &str) -> Option<BumpKind> {
+    let subject = message.lines().next()?.trim();
+    let (header, _) = subject.split_once(':')?;
+
+    if header.ends_with('!')
+        || message
+            .lines()
+            .any(|l| l.starts_with("BREAKING CHANGE:") || l.starts_with("BREAKING-CHANGE:"))
+    {
+        return Some(BumpKind::Major);
+    }
+
+    // strip an optional scope, `feat(ui)` -> `feat`
+    let kind = header.split('(').next().unwrap_or(header).trim();
+    match kind {
+        "feat" => Some(BumpKind::Minor),
+        "fix" => Some(BumpKind::Patch),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infers_the_largest_bump() {
+        for (messages, expected) in [
+            (vec!["fix: crash on empty song"], Some(BumpKind::Patch)),
+            (vec!["feat(ui): add a dialog"], Some(BumpKind::Minor)),
+            (vec!["fix: typo", "feat: presets"], Some(BumpKind::Minor)),
+            (vec!["feat!: drop the old API"], Some(BumpKind::Major)),
+            (vec!["fix(core)!: rename the tool"], Some(BumpKind::Major)),
+            (
+                vec!["fix: rename\n\nBREAKING CHANGE: the menu entry moved"],
+                Some(BumpKind::Major),
+            ),
+            (
+                vec!["feat: presets\n\nBREAKING-CHANGE: new file format"],
+                Some(BumpKind::Major),
+            ),
+            (vec!["feat: presets", "fix: typo"], Some(BumpKind::Minor)),
+            (vec!["docs: readme", "chore: tidy up"], None),
+            (vec!["update things", "featured: not a type"], None),
+            (vec![], None),
+        ] {
+            assert_eq!(
+                infer_bump(messages.iter().copied()),
+                expected,
+                "{messages:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use crate::manifest::read_manifest;
+use serde::Deserialize;
+
+/// JSON shapes accepted for an update feed besides a plain manifest.xml.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Feed {
+    List(Vec<String>),
+    Versions { versions: Vec<String> },
+    Latest { version: String },
+}
+
+/// Versions published at `url`, which serves either a tool manifest or JSON
+/// (`["1.0", ...]`, `{"versions": [...]}` or `{"version": "1.0"}`).
+pub fn published_versions(url: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let body = ureq::get(url)
+        .call()
+        .map_err(|e| format!("failed to fetch update feed {url}: {e}"))?
+        .into_string()?;
+
+    if body.trim_start().starts_with('<') {
+        let manifest = read_manifest(&body)
+            .map_err(|e| format!("update feed {url} is not a valid manifest: {e}"))?;
+        return Ok(manifest.version.into_iter().collect());
+    }
+    let feed: Feed = serde_json::from_str(&body)
+        .map_err(|e| format!("update feed {url} is neither a manifest nor a version list: {e}"))?;
+    Ok(match feed {
+        Feed::List(versions) | Feed::Versions { versions } => versions,
+        Feed::Latest { version } => vec![version],
+    })
+}
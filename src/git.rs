@@ -0,0 +1,77 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Runs `git` in `root` and returns its trimmed stdout.
+pub fn git(root: &Path, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git {} failed: {}", args.join(" "), stderr.trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub fn tag_exists(root: &Path, tag: &str) -> bool {
+    git(
+        root,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("refs/tags/{tag}"),
+        ],
+    )
+    .is_ok()
+}
+
+/// `owner/repo` of the GitHub remote `origin`, from either an https or ssh URL.
+pub fn github_repo(root: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    let url = git(root, &["remote", "get-url", "origin"])?;
+    let path = url
+        .strip_prefix("git@github.com:")
+        .or_else(|| url.strip_prefix("https://github.com/"))
+        .or_else(|| url.strip_prefix("ssh://git@github.com/"))
+        .ok_or_else(|| format!("remote origin '{url}' is not a GitHub repository"))?;
+    Ok(path
+        .trim_end_matches('/')
+        .trim_end_matches(".git")
+        .to_string())
+}
+
+/// All tags matching `pattern`, empty outside a git repository.
+pub fn tags(root: &Path, pattern: &str) -> Vec<String> {
+    git(root, &["tag", "--list", pattern])
+        .map(|out| out.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Most recent tag reachable from HEAD matching `pattern`, if any.
+pub fn last_tag(root: &Path, pattern: &str) -> Option<String> {
+    git(
+        root,
+        &["describe", "--tags", "--abbrev=0", "--match", pattern],
+    )
+    .ok()
+}
+
+/// Full messages of the commits after `since` (or of all commits when `None`).
+pub fn commit_messages(
+    root: &Path,
+    since: Option<&str>,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let range = match since {
+        Some(tag) => format!("{tag}..HEAD"),
+        None => "HEAD".to_string(),
+    };
+    // %x1e (record separator) keeps multi-line bodies together
+    let log = git(root, &["log", "--format=%B%x1e", &range])?;
+    Ok(log
+        .split('\x1e')
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .collect())
+}
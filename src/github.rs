@@ -0,0 +1,27 @@
+use serde::Deserialize;
+
+pub const API: &str = "https://api.github.com";
+
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub html_url: String,
+    pub upload_url: String,
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+/// The newest non-draft, non-prerelease release of `repo` (`owner/name`).
+pub fn latest_release(repo: &str) -> Result<Release, Box<dyn std::error::Error>> {
+    let release = ureq::get(&format!("{API}/repos/{repo}/releases/latest"))
+        .set("Accept", "application/vnd.github+json")
+        .call()?
+        .into_json()?;
+    Ok(release)
+}
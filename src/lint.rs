@@ -0,0 +1,139 @@
+use crate::lua::identifier;
+use full_moon::ast::{Call, FunctionCall, Index, Prefix, Suffix, Var};
+use full_moon::tokenizer::TokenReference;
+use full_moon::visitors::Visitor;
+use full_moon::{LuaVersion, parse_fallible};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Globals the Renoise scripting API adds to Lua 5.1.
+const RENOISE_GLOBALS: &[&str] = &[
+    "renoise",
+    "class",
+    "property",
+    "oprint",
+    "rprint",
+    "objinfo",
+    "ripairs",
+    "bit",
+    "ObservableBoolean",
+    "ObservableNumber",
+    "ObservableString",
+    "ObservableBooleanList",
+    "ObservableNumberList",
+    "ObservableStringList",
+];
+
+/// Runs luacheck over `files` (relative to `root`) with the Renoise globals
+/// known, returning its warnings as `<file>:<line>:<column>: (<code>) <message>`.
+/// A `.luacheckrc` in the project is picked up as usual.
+pub fn luacheck(root: &Path, files: &[PathBuf]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+    let output = Command::new("luacheck")
+        .current_dir(root)
+        .args(["--std", "lua51", "--formatter", "plain", "--codes"])
+        .arg("--read-globals")
+        .args(RENOISE_GLOBALS)
+        .arg("--")
+        .args(files)
+        .output()
+        .map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                "luacheck not found, install it to lint the tool".to_string()
+            } else {
+                format!("cannot run luacheck: {e}")
+            }
+        })?;
+    // 1 means warnings, 2 errors in the files, anything above that luacheck itself failed
+    if output.status.code().is_none_or(|code| code > 2) {
+        return Err(format!(
+            "luacheck failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Places in `code` that use one of `banned`, dotted names like `os.execute`
+/// or plain globals like `loadstring`, whether called or not, as
+/// `<name>:<line>:<column>: <function> is banned`. Files that don't parse are
+/// left to the syntax check.
+pub fn banned_calls(name: &str, code: &str, banned: &[String]) -> Vec<String> {
+    if banned.is_empty() {
+        return Vec::new();
+    }
+    let code = code.strip_prefix('\u{feff}').unwrap_or(code);
+    let Ok(ast) = parse_fallible(code, LuaVersion::lua51()).into_result() else {
+        return Vec::new();
+    };
+    let mut uses = BannedUses {
+        banned,
+        found: BTreeMap::new(),
+    };
+    uses.visit_ast(&ast);
+    uses.found
+        .into_iter()
+        .map(|((line, column), function)| format!("{name}:{line}:{column}: {function} is banned"))
+        .collect()
+}
+
+struct BannedUses<'a> {
+    banned: &'a [String],
+    /// Banned functions used, by position.
+    found: BTreeMap<(usize, usize), String>,
+}
+
+impl BannedUses<'_> {
+    /// Checks the names `root` and the `.name` or `:name` `suffixes` after it
+    /// spell out.
+    fn check<'a>(&mut self, root: &TokenReference, mut suffixes: impl Iterator<Item = &'a Suffix>) {
+        let Some(mut path) = identifier(root).map(str::to_string) else {
+            return;
+        };
+        loop {
+            if self.banned.contains(&path) {
+                let at = root.start_position();
+                self.found.insert((at.line(), at.character()), path);
+                return;
+            }
+            let (separator, name) = match suffixes.next() {
+                Some(Suffix::Index(Index::Dot { name, .. })) => ('.', name),
+                Some(Suffix::Call(Call::MethodCall(call))) => (':', call.name()),
+                _ => return,
+            };
+            path.push(separator);
+            path.push_str(identifier(name).unwrap_or_default());
+        }
+    }
+}
+
+impl Visitor for BannedUses<'_> {
+    fn visit_function_call(&mut self, call: &FunctionCall) {
+        if let Prefix::Name(root) = call.prefix() {
+            self.check(root, call.suffixes());
+        }
+    }
+
+    fn visit_var(&mut self, var: &Var) {
+        match var {
+            Var::Name(root) => self.check(root, std::iter::empty()),
+            Var::Expression(var) => {
+                if let Prefix::Name(root) = var.prefix() {
+                    self.check(root, var.suffixes());
+                }
+            }
+            _ => {}
+        }
+    }
+}
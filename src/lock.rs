@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Record of the last release, kept in the project root next to the manifest.
+pub const LOCK_FILE: &str = "rnplug.lock";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ReleaseLock {
+    /// Version of the last packaged release, empty before the first one.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub version: String,
+
+    /// SHA-256 of every file in that release, by archive entry name.
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+
+    /// Last version built on each `--channel` stream.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub channels: BTreeMap<String, String>,
+}
+
+pub fn load(root: &Path) -> Result<Option<ReleaseLock>, Box<dyn std::error::Error>> {
+    let path = root.join(LOCK_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let lock = toml::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| format!("invalid {LOCK_FILE}: {e}"))?;
+    Ok(Some(lock))
+}
+
+pub fn save(root: &Path, lock: &ReleaseLock) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(root.join(LOCK_FILE), toml::to_string(lock)?)?;
+    Ok(())
+}
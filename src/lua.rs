@@ -0,0 +1,31 @@
+use full_moon::LuaVersion;
+use full_moon::tokenizer::{TokenReference, TokenType};
+
+/// `<name>:<line>:<column>: <message>` for every Lua 5.1 syntax error in `code`,
+/// the version Renoise runs tools with, in the order they appear.
+pub fn syntax_errors(name: &str, code: &str) -> Vec<String> {
+    let code = code.strip_prefix('\u{feff}').unwrap_or(code);
+    let Err(errors) = full_moon::parse_fallible(code, LuaVersion::lua51()).into_result() else {
+        return Vec::new();
+    };
+    let mut errors: Vec<_> = errors
+        .iter()
+        .map(|error| {
+            let (start, _) = error.range();
+            (start.line(), start.character(), error.error_message())
+        })
+        .collect();
+    errors.sort();
+    errors
+        .into_iter()
+        .map(|(line, column, message)| format!("{name}:{line}:{column}: {message}"))
+        .collect()
+}
+
+/// The name `token` spells, if it is an identifier.
+pub fn identifier(token: &TokenReference) -> Option<&str> {
+    match token.token_type() {
+        TokenType::Identifier { identifier } => Some(identifier.as_str()),
+        _ => None,
+    }
+}
@@ -0,0 +1,632 @@
+use crate::renoise::ApiVersion;
+use crate::schema;
+use crate::version::parse_version;
+use quick_xml::Reader;
+use quick_xml::de::from_str;
+use quick_xml::escape::partial_escape;
+use quick_xml::events::Event;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Manifest {
+    #[serde(rename = "@doc_version")]
+    pub doc_version: Option<u32>,
+    pub api_version: Option<ApiVersion>,
+    pub author: Option<String>,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub homepage: Option<String>,
+    pub icon: Option<String>,
+    pub platform: Option<String>,
+    pub auto_upgrade: Option<bool>,
+    /// Top-level elements outside the known set, by name, so they are not lost
+    /// when showing or comparing manifests.
+    #[serde(skip)]
+    pub other: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// Every known field in document order, rendered for display.
+    pub fn fields(&self) -> Vec<(&'static str, Option<String>)> {
+        vec![
+            ("doc_version", self.doc_version.map(|v| v.to_string())),
+            ("ApiVersion", self.api_version.map(|v| v.to_string())),
+            ("Id", self.id.clone()),
+            ("Name", self.name.clone()),
+            ("Version", self.version.clone()),
+            ("Author", self.author.clone()),
+            ("Description", self.description.clone()),
+            ("Category", self.category.clone()),
+            ("Homepage", self.homepage.clone()),
+            ("Icon", self.icon.clone()),
+            ("Platform", self.platform.clone()),
+            ("AutoUpgrade", self.auto_upgrade.map(|v| v.to_string())),
+        ]
+    }
+
+    /// [`Manifest::fields`] followed by the unknown elements, for display.
+    pub fn all_fields(&self) -> Vec<(String, Option<String>)> {
+        let known = self
+            .fields()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value));
+        let other = self
+            .other
+            .iter()
+            .map(|(name, value)| (name.clone(), Some(value.clone())));
+        known.chain(other).collect()
+    }
+}
+
+/// Text encoding a manifest file was saved in, kept when writing it back.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        match self {
+            Encoding::Utf8 => text.as_bytes().to_vec(),
+            Encoding::Utf8Bom => [&[0xEF, 0xBB, 0xBF], text.as_bytes()].concat(),
+            Encoding::Utf16Le => [0xFEFF_u16]
+                .into_iter()
+                .chain(text.encode_utf16())
+                .flat_map(u16::to_le_bytes)
+                .collect(),
+            Encoding::Utf16Be => [0xFEFF_u16]
+                .into_iter()
+                .chain(text.encode_utf16())
+                .flat_map(u16::to_be_bytes)
+                .collect(),
+        }
+    }
+}
+
+/// Decodes a manifest saved as UTF-8 (with or without BOM) or UTF-16. UTF-16
+/// without a BOM is recognised by the leading `<`.
+pub fn decode(bytes: &[u8]) -> Result<(String, Encoding), String> {
+    let (encoding, body) = match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => (Encoding::Utf8Bom, rest),
+        [0xFF, 0xFE, rest @ ..] => (Encoding::Utf16Le, rest),
+        [0xFE, 0xFF, rest @ ..] => (Encoding::Utf16Be, rest),
+        [b'<', 0, ..] => (Encoding::Utf16Le, bytes),
+        [0, b'<', ..] => (Encoding::Utf16Be, bytes),
+        _ => (Encoding::Utf8, bytes),
+    };
+    let text = match encoding {
+        Encoding::Utf8 | Encoding::Utf8Bom => String::from_utf8(body.to_vec())
+            .map_err(|e| format!("not valid UTF-8 ({e}) and no UTF-16 byte order mark"))?,
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            if body.len() % 2 != 0 {
+                return Err("truncated UTF-16 (odd number of bytes)".to_string());
+            }
+            let units: Vec<u16> = body
+                .chunks_exact(2)
+                .map(|pair| match encoding {
+                    Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                })
+                .collect();
+            String::from_utf16(&units).map_err(|e| format!("invalid UTF-16: {e}"))?
+        }
+    };
+    Ok((text, encoding))
+}
+
+/// Reads a manifest file in whatever encoding it was saved with.
+pub fn read_file(path: &Path) -> io::Result<(String, Encoding)> {
+    decode(&fs::read(path)?).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {e}", path.display()),
+        )
+    })
+}
+
+/// Writes `text` back to a manifest file in `encoding`.
+pub fn write_file(path: &Path, text: &str, encoding: Encoding) -> io::Result<()> {
+    fs::write(path, encoding.encode(text))
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Xml(quick_xml::DeError),
+    MissingField(&'static str),
+    Rewrite(quick_xml::Error),
+    /// Everything wrong with the required fields, found in one pass.
+    Invalid(Vec<FieldProblem>),
+}
+
+/// One problem found by [`parse_manifest`].
+#[derive(Debug)]
+pub enum FieldProblem {
+    Missing(&'static str),
+    Invalid {
+        field: &'static str,
+        message: String,
+    },
+    /// A schema violation reported by [`schema::check`], with its position.
+    Schema(String),
+}
+
+impl std::fmt::Display for FieldProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldProblem::Missing(field) => write!(f, "missing required field `{field}`"),
+            FieldProblem::Invalid { field, message } => write!(f, "invalid `{field}`: {message}"),
+            FieldProblem::Schema(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManifestError::Xml(e) => write!(f, "XML error: {e}"),
+            ManifestError::MissingField(field) => write!(f, "missing required field `{field}`"),
+            ManifestError::Rewrite(e) => write!(f, "cannot rewrite manifest: {e}"),
+            ManifestError::Invalid(problems) => {
+                write!(f, "{} problem(s)", problems.len())?;
+                for problem in problems {
+                    write!(f, "\n  - {problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+pub fn read_manifest(contents: &str) -> Result<Manifest, ManifestError> {
+    let mut manifest: Manifest = from_str(contents).map_err(ManifestError::Xml)?;
+    for name in element_names(contents)? {
+        if schema::field_type(&name).is_none()
+            && let Some(value) = get_field(contents, &name)?
+        {
+            manifest.other.insert(name, value);
+        }
+    }
+    Ok(manifest)
+}
+
+/// Names of the root's child elements in document order.
+fn element_names(contents: &str) -> Result<Vec<String>, ManifestError> {
+    let mut reader = Reader::from_str(contents);
+    let mut depth = 0usize;
+    let mut names = Vec::new();
+    loop {
+        match reader.read_event().map_err(ManifestError::Rewrite)? {
+            Event::Start(e) => {
+                if depth == 1 {
+                    names.push(String::from_utf8_lossy(e.local_name().as_ref()).into_owned());
+                }
+                depth += 1;
+            }
+            Event::Empty(e) if depth == 1 => {
+                names.push(String::from_utf8_lossy(e.local_name().as_ref()).into_owned());
+            }
+            Event::End(_) => depth -= 1,
+            Event::Eof => return Ok(names),
+            _ => {}
+        }
+    }
+}
+
+/// Id and version of a manifest. Every missing or malformed required field is
+/// reported at once in [`ManifestError::Invalid`].
+pub fn parse_manifest(contents: &str) -> Result<(String, String), ManifestError> {
+    let manifest = match read_manifest(contents) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            // serde stops at the first problem, the schema check finds them all
+            let problems: Vec<_> = schema::check(contents)
+                .into_iter()
+                .map(FieldProblem::Schema)
+                .collect();
+            return Err(if problems.is_empty() {
+                e
+            } else {
+                ManifestError::Invalid(problems)
+            });
+        }
+    };
+
+    let mut problems = Vec::new();
+    if manifest.id.is_none() {
+        problems.push(FieldProblem::Missing("Id"));
+    }
+    match &manifest.version {
+        None => problems.push(FieldProblem::Missing("Version")),
+        Some(version) => {
+            if let Err(e) = parse_version(version) {
+                problems.push(FieldProblem::Invalid {
+                    field: "Version",
+                    message: format!("'{version}' is not a version: {e}"),
+                });
+            }
+        }
+    }
+    match (manifest.id, manifest.version) {
+        (Some(id), Some(version)) if problems.is_empty() => Ok((id, version)),
+        _ => Err(ManifestError::Invalid(problems)),
+    }
+}
+
+/// Where a top-level element of the manifest sits in the source text.
+struct Located {
+    /// The whole element, tags included.
+    outer: Range<usize>,
+    /// Its text content, `None` for a self-closing `<Field/>`.
+    inner: Option<Range<usize>>,
+}
+
+/// Byte positions needed to edit the child `name` of the root element.
+struct Layout {
+    field: Option<Located>,
+    /// Just after the last child element, where a new one is inserted.
+    insert_at: usize,
+    /// Whitespace in front of the first child, reused for inserted elements.
+    indent: String,
+}
+
+fn layout(contents: &str, name: &str) -> Result<Layout, ManifestError> {
+    let mut reader = Reader::from_str(contents);
+    let mut depth = 0usize;
+    let mut field = None;
+    let mut element_start = 0;
+    let mut text_start = None;
+    let mut insert_at = None;
+    let mut indent = None;
+    let root_end;
+    loop {
+        let before = reader.buffer_position();
+        match reader.read_event().map_err(ManifestError::Rewrite)? {
+            Event::Start(e) => {
+                if depth == 1 {
+                    element_start = before;
+                    if field.is_none() && e.local_name().as_ref() == name.as_bytes() {
+                        text_start = Some(reader.buffer_position());
+                    }
+                }
+                depth += 1;
+            }
+            Event::End(_) => {
+                depth -= 1;
+                match depth {
+                    0 => {
+                        root_end = before;
+                        break;
+                    }
+                    1 => {
+                        if let Some(start) = text_start.take() {
+                            field = Some(Located {
+                                outer: element_start..reader.buffer_position(),
+                                inner: Some(start..before),
+                            });
+                        }
+                        insert_at = Some(reader.buffer_position());
+                    }
+                    _ => {}
+                }
+            }
+            Event::Empty(e) if depth == 1 => {
+                if field.is_none() && e.local_name().as_ref() == name.as_bytes() {
+                    field = Some(Located {
+                        outer: before..reader.buffer_position(),
+                        inner: None,
+                    });
+                }
+                insert_at = Some(reader.buffer_position());
+            }
+            Event::Text(_) if depth == 1 && indent.is_none() => {
+                let text = &contents[before..reader.buffer_position()];
+                indent = Some(text.rsplit('\n').next().unwrap_or_default().to_string());
+            }
+            Event::Eof => return Err(ManifestError::MissingField("RenoiseScriptingTool")),
+            _ => {}
+        }
+    }
+    Ok(Layout {
+        field,
+        insert_at: insert_at.unwrap_or(root_end),
+        indent: indent.unwrap_or_else(|| "  ".to_string()),
+    })
+}
+
+/// Text of the top-level element `name`, `None` if it is missing.
+pub fn get_field(contents: &str, name: &str) -> Result<Option<String>, ManifestError> {
+    let Some(field) = layout(contents, name)?.field else {
+        return Ok(None);
+    };
+    let Some(inner) = field.inner else {
+        return Ok(Some(String::new()));
+    };
+    let mut reader = Reader::from_str(&contents[inner]);
+    let mut text = String::new();
+    loop {
+        match reader.read_event().map_err(ManifestError::Rewrite)? {
+            Event::Text(t) => text.push_str(&t.unescape().map_err(ManifestError::Rewrite)?),
+            Event::CData(t) => text.push_str(&String::from_utf8_lossy(&t)),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(Some(text))
+}
+
+/// Sets (or with `None` removes) the top-level element `name`. Everything
+/// outside that element is kept byte for byte, comments and indentation
+/// included; a new element is added after the last one, indented like the
+/// first.
+pub fn set_field(contents: &str, name: &str, value: Option<&str>) -> Result<String, ManifestError> {
+    let layout = layout(contents, name)?;
+    match (layout.field, value) {
+        (
+            Some(Located {
+                inner: Some(inner), ..
+            }),
+            Some(value),
+        ) => Ok(splice(contents, inner, &partial_escape(value))),
+        (Some(Located { outer, .. }), Some(value)) => {
+            let element = format!("<{name}>{}</{name}>", partial_escape(value));
+            Ok(splice(contents, outer, &element))
+        }
+        (Some(Located { outer, .. }), None) => {
+            // take the element's own line with it
+            let line_start = contents[..outer.start]
+                .trim_end_matches([' ', '\t'])
+                .strip_suffix('\n')
+                .map(|rest| rest.strip_suffix('\r').unwrap_or(rest).len());
+            let start = line_start.unwrap_or(outer.start);
+            Ok(splice(contents, start..outer.end, ""))
+        }
+        (None, Some(value)) => {
+            let newline = if contents.contains("\r\n") {
+                "\r\n"
+            } else {
+                "\n"
+            };
+            let element = format!(
+                "{newline}{}<{name}>{}</{name}>",
+                layout.indent,
+                partial_escape(value)
+            );
+            Ok(splice(
+                contents,
+                layout.insert_at..layout.insert_at,
+                &element,
+            ))
+        }
+        (None, None) => Ok(contents.to_string()),
+    }
+}
+
+/// Starting point for a manifest generated from rnplug.toml.
+const SKELETON: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<RenoiseScriptingTool doc_version="0">
+  <Version>0.1</Version>
+</RenoiseScriptingTool>
+"#;
+
+/// Writes `fields` into `contents`, or into a fresh manifest when there is none yet.
+pub fn render(contents: Option<&str>, fields: &[(&str, String)]) -> Result<String, ManifestError> {
+    let mut out = contents.unwrap_or(SKELETON).to_string();
+    for (name, value) in fields {
+        out = set_field(&out, name, Some(value))?;
+    }
+    Ok(out)
+}
+
+/// Adds the `doc_version` attribute to the root element when it is missing.
+pub fn ensure_doc_version(contents: &str) -> Result<String, ManifestError> {
+    let mut reader = Reader::from_str(contents);
+    loop {
+        match reader.read_event().map_err(ManifestError::Rewrite)? {
+            Event::Start(e) | Event::Empty(e) => {
+                let has_doc_version = e
+                    .attributes()
+                    .flatten()
+                    .any(|a| a.key.as_ref() == b"doc_version");
+                if has_doc_version {
+                    return Ok(contents.to_string());
+                }
+                // insert just after the element name, the closing `>` or `/>` stays put
+                let name_end = contents[..reader.buffer_position()]
+                    .rfind('<')
+                    .map(|start| start + 1 + e.name().as_ref().len())
+                    .ok_or(ManifestError::MissingField("RenoiseScriptingTool"))?;
+                let attribute = format!(" doc_version=\"{}\"", schema::DOC_VERSION);
+                return Ok(splice(contents, name_end..name_end, &attribute));
+            }
+            Event::Eof => return Err(ManifestError::MissingField("RenoiseScriptingTool")),
+            _ => {}
+        }
+    }
+}
+
+/// Rewrites the text of the root's `<Version>` element to `new_version`,
+/// leaving the rest of the document untouched.
+pub fn set_version(contents: &str, new_version: &str) -> Result<String, ManifestError> {
+    if layout(contents, "Version")?.field.is_none() {
+        return Err(ManifestError::MissingField("Version"));
+    }
+    set_field(contents, "Version", Some(new_version))
+}
+
+fn splice(contents: &str, range: Range<usize>, replacement: &str) -> String {
+    let mut out = String::with_capacity(contents.len() + replacement.len());
+    out.push_str(&contents[..range.start]);
+    out.push_str(replacement);
+    out.push_str(&contents[range.end..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_version_keeps_odd_formatting() {
+        let manifest = "<?xml version=\"1.0\"?>\r\n<RenoiseScriptingTool doc_version=\"0\">\r\n\t<!-- <Version>0.0</Version> -->\r\n\t<Version  >1.0</Version >\r\n\t<Name>Tool</Name>\r\n</RenoiseScriptingTool>\r\n";
+        let updated = set_version(manifest, "1.1").unwrap();
+        assert_eq!(updated, manifest.replace(">1.0<", ">1.1<"));
+    }
+
+    #[test]
+    fn set_version_only_touches_the_top_level_element() {
+        let manifest = r#"<RenoiseScriptingTool doc_version="0">
+  <Description><Version>keep</Version></Description>
+  <Version lang="x">1.0</Version>
+  <Version>2.0</Version>
+</RenoiseScriptingTool>"#;
+        let updated = set_version(manifest, "1.1").unwrap();
+        assert_eq!(updated, manifest.replace(r#""x">1.0<"#, r#""x">1.1<"#));
+    }
+
+    #[test]
+    fn set_version_fills_empty_elements() {
+        let manifest = "<RenoiseScriptingTool><Version/></RenoiseScriptingTool>";
+        assert_eq!(
+            set_version(manifest, "1.0").unwrap(),
+            "<RenoiseScriptingTool><Version>1.0</Version></RenoiseScriptingTool>"
+        );
+    }
+
+    #[test]
+    fn set_version_needs_a_version() {
+        let manifest = "<RenoiseScriptingTool><Name>Tool</Name></RenoiseScriptingTool>";
+        assert!(matches!(
+            set_version(manifest, "1.0"),
+            Err(ManifestError::MissingField("Version"))
+        ));
+    }
+
+    #[test]
+    fn set_field_escapes_and_inserts_with_indentation() {
+        let manifest = "<RenoiseScriptingTool>\n    <Id>com.x.Tool</Id>\n</RenoiseScriptingTool>\n";
+        let updated = set_field(manifest, "Name", Some("A & B")).unwrap();
+        assert_eq!(
+            updated,
+            "<RenoiseScriptingTool>\n    <Id>com.x.Tool</Id>\n    <Name>A &amp; B</Name>\n</RenoiseScriptingTool>\n"
+        );
+        assert_eq!(
+            get_field(&updated, "Name").unwrap().as_deref(),
+            Some("A & B")
+        );
+        assert_eq!(set_field(&updated, "Name", None).unwrap(), manifest);
+    }
+
+    #[test]
+    fn get_field_reads_cdata() {
+        let manifest =
+            "<RenoiseScriptingTool><Version><![CDATA[1.0]]></Version></RenoiseScriptingTool>";
+        assert_eq!(
+            get_field(manifest, "Version").unwrap().as_deref(),
+            Some("1.0")
+        );
+    }
+
+    #[test]
+    fn set_version_replaces_cdata() {
+        let manifest = "<RenoiseScriptingTool><Version><![CDATA[1.0]]></Version><Name>T</Name></RenoiseScriptingTool>";
+        assert_eq!(
+            set_version(manifest, "1.1").unwrap(),
+            "<RenoiseScriptingTool><Version>1.1</Version><Name>T</Name></RenoiseScriptingTool>"
+        );
+    }
+
+    #[test]
+    fn keeps_escaped_values_escaped() {
+        let manifest = "<RenoiseScriptingTool><Id>com.a&amp;b</Id><Version>1.0</Version><Name>A &amp; B &lt;x&gt;</Name></RenoiseScriptingTool>";
+        assert_eq!(
+            parse_manifest(manifest).unwrap(),
+            ("com.a&b".to_string(), "1.0".to_string())
+        );
+        assert_eq!(
+            get_field(manifest, "Name").unwrap().as_deref(),
+            Some("A & B <x>")
+        );
+        // other fields come through a version bump untouched
+        assert_eq!(
+            set_version(manifest, "1.1").unwrap(),
+            manifest.replace(">1.0<", ">1.1<")
+        );
+        // a value is text, entities in it are escaped once more
+        let updated = set_field(manifest, "Name", Some("C &amp; D")).unwrap();
+        assert!(updated.contains("<Name>C &amp;amp; D</Name>"), "{updated}");
+        assert_eq!(
+            get_field(&updated, "Name").unwrap().as_deref(),
+            Some("C &amp; D")
+        );
+    }
+
+    #[test]
+    fn parse_manifest_needs_a_version() {
+        let manifest = "<RenoiseScriptingTool><Id>com.x.Tool</Id></RenoiseScriptingTool>";
+        let error = parse_manifest(manifest).unwrap_err().to_string();
+        assert!(error.contains("Version"), "{error}");
+    }
+
+    #[test]
+    fn decodes_boms_and_utf16() {
+        let text = "<RenoiseScriptingTool><Name>Grüße</Name></RenoiseScriptingTool>";
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf8Bom,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+        ] {
+            assert_eq!(
+                decode(&encoding.encode(text)),
+                Ok((text.to_string(), encoding)),
+                "{encoding:?}"
+            );
+        }
+        // UTF-16 without a byte order mark
+        let bare: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(decode(&bare), Ok((text.to_string(), Encoding::Utf16Le)));
+        assert!(decode(&bare[..bare.len() - 1]).is_err());
+        assert!(decode(&[0xFF, 0xFE, 0x00, 0xD8]).is_err());
+        assert!(decode(&[b'<', 0xFF]).is_err());
+    }
+
+    #[test]
+    fn round_trips_utf16le_manifests() {
+        let path = std::env::temp_dir().join(format!("rnplug-utf16-{}.xml", std::process::id()));
+        let manifest = "<?xml version=\"1.0\" encoding=\"UTF-16\"?>\r\n<RenoiseScriptingTool doc_version=\"0\">\r\n  <Id>com.example.Grüße</Id>\r\n  <Version>1.0</Version>\r\n</RenoiseScriptingTool>\r\n";
+        fs::write(&path, Encoding::Utf16Le.encode(manifest)).unwrap();
+
+        let (contents, encoding) = read_file(&path).unwrap();
+        assert_eq!(encoding, Encoding::Utf16Le);
+        assert_eq!(
+            parse_manifest(&contents).unwrap(),
+            ("com.example.Grüße".to_string(), "1.0".to_string())
+        );
+        // the byte order mark is not part of the text the linter sees
+        assert!(contents.starts_with("<?xml"));
+        assert!(crate::schema::check(&contents).is_empty());
+
+        write_file(&path, &set_version(&contents, "1.1").unwrap(), encoding).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            bytes,
+            Encoding::Utf16Le.encode(&manifest.replace(">1.0<", ">1.1<"))
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use crate::git::git;
+use std::path::Path;
+use time::OffsetDateTime;
+
+/// Values for the `{{VERSION}}`, `{{BUILD_DATE}}` and `{{GIT_SHA}}` placeholders
+/// of packaged sources.
+pub struct BuildInfo {
+    pub version: String,
+    /// `YYYY-MM-DD` in UTC, of `SOURCE_DATE_EPOCH` if set so builds stay reproducible.
+    pub date: String,
+    /// Short hash of HEAD, `unknown` outside a git repository.
+    pub sha: String,
+}
+
+impl BuildInfo {
+    pub fn current(root: &Path, version: &str) -> Self {
+        let today = std::env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|epoch| OffsetDateTime::from_unix_timestamp(epoch.trim().parse().ok()?).ok())
+            .unwrap_or_else(OffsetDateTime::now_utc)
+            .date();
+        BuildInfo {
+            version: version.to_string(),
+            date: format!(
+                "{:04}-{:02}-{:02}",
+                today.year(),
+                u8::from(today.month()),
+                today.day()
+            ),
+            sha: git(root, &["rev-parse", "--short", "HEAD"])
+                .unwrap_or_else(|_| "unknown".to_string()),
+        }
+    }
+
+    /// A Lua module returning the same values as `version`, `build_date` and
+    /// `git_sha`, along with the pre-release `channel` (`nil` for releases).
+    pub fn lua_module(&self, channel: Option<&str>) -> String {
+        format!(
+            "-- Generated by rnplug when packaging.\nreturn {{\n  version = {},\n  build_date = {},\n  git_sha = {},\n  channel = {},\n}}\n",
+            lua_string(&self.version),
+            lua_string(&self.date),
+            lua_string(&self.sha),
+            channel.map_or("nil".to_string(), lua_string)
+        )
+    }
+
+    /// `text` with every placeholder replaced.
+    pub fn apply(&self, text: &str) -> String {
+        text.replace("{{VERSION}}", &self.version)
+            .replace("{{BUILD_DATE}}", &self.date)
+            .replace("{{GIT_SHA}}", &self.sha)
+    }
+}
+
+/// `text` as a double-quoted Lua string literal.
+pub fn lua_string(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                literal.push('\\');
+                literal.push(c);
+            }
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            _ => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
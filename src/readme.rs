@@ -0,0 +1,41 @@
+/// Marks the part of the README used as the manifest Description.
+pub const START_MARKER: &str = "<!-- description -->";
+pub const END_MARKER: &str = "<!-- /description -->";
+
+/// The text between the description markers, or else the first paragraph that
+/// is not a heading, badge line or HTML, joined into a single line.
+pub fn description(readme: &str) -> Option<String> {
+    if let Some((_, rest)) = readme.split_once(START_MARKER)
+        && let Some((section, _)) = rest.split_once(END_MARKER)
+    {
+        return Some(join_lines(section)).filter(|d| !d.is_empty());
+    }
+
+    let mut paragraph = Vec::new();
+    for line in readme.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            if !paragraph.is_empty() {
+                break;
+            }
+            continue;
+        }
+        let skipped = line.starts_with('#')
+            || line.starts_with("![")
+            || line.starts_with("[![")
+            || line.starts_with('<')
+            || line.chars().all(|c| c == '=' || c == '-');
+        if skipped {
+            if !paragraph.is_empty() {
+                break;
+            }
+            continue;
+        }
+        paragraph.push(line);
+    }
+    Some(join_lines(&paragraph.join("\n"))).filter(|d| !d.is_empty())
+}
+
+fn join_lines(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,188 @@
+use crate::version::parse_version;
+use semver::Version;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Directory holding one `V<x.y.z>` folder per installed Renoise version.
+pub fn preferences_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(|d| PathBuf::from(d).join("Renoise"))
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|d| PathBuf::from(d).join("Library/Preferences/Renoise"))
+    } else {
+        env::var_os("HOME").map(|d| PathBuf::from(d).join(".renoise"))
+    }
+}
+
+/// All `V<x.y.z>` preference folders, newest first.
+pub fn version_dirs() -> Vec<(Version, PathBuf)> {
+    let Some(prefs) = preferences_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(prefs) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            let version = parse_version(name.strip_prefix('V')?).ok()?;
+            Some((version, e.path()))
+        })
+        .collect();
+    dirs.sort_by(|a, b| b.0.cmp(&a.0));
+    dirs
+}
+
+/// `Scripts/Tools` folder of the requested Renoise version, or of the newest one
+/// when no version is given.
+pub fn tools_dir(renoise_version: Option<&str>) -> Option<PathBuf> {
+    let dirs = version_dirs();
+    let dir = match renoise_version {
+        Some(wanted) => {
+            let wanted = parse_version(wanted.trim_start_matches('V')).ok()?;
+            dirs.into_iter().find(|(v, _)| *v == wanted)?.1
+        }
+        None => dirs.into_iter().next()?.1,
+    };
+    Some(dir.join("Scripts").join("Tools"))
+}
+
+/// A scripting `ApiVersion`, `major` or `major.minor` like `6` or `6.1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ApiVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl ApiVersion {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        ApiVersion { major, minor }
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.minor {
+            0 => write!(f, "{}", self.major),
+            minor => write!(f, "{}.{minor}", self.major),
+        }
+    }
+}
+
+impl FromStr for ApiVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid ApiVersion '{s}', expected e.g. 6 or 6.1");
+        let (major, minor) = s.trim().split_once('.').unwrap_or((s.trim(), "0"));
+        let number = |part: &str| {
+            (!part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+                .then(|| part.parse().ok())
+                .flatten()
+                .ok_or_else(invalid)
+        };
+        Ok(ApiVersion::new(number(major)?, number(minor)?))
+    }
+}
+
+impl Serialize for ApiVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Read from text like `6.1`, see [`toml_api_version`] for rnplug.toml.
+impl<'de> Deserialize<'de> for ApiVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Parse;
+
+        impl Visitor<'_> for Parse {
+            type Value = ApiVersion;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an ApiVersion such as 6 or 6.1")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<ApiVersion, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(Parse)
+    }
+}
+
+/// Reads `api_version = 6`, `6.1` or `"6.1"` from rnplug.toml.
+pub fn toml_api_version<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<ApiVersion>, D::Error> {
+    let text = match Option::<toml::Value>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(toml::Value::Integer(v)) => v.to_string(),
+        Some(toml::Value::Float(v)) => v.to_string(),
+        Some(toml::Value::String(v)) => v,
+        Some(other) => {
+            return Err(de::Error::custom(format!(
+                "expected an ApiVersion such as 6 or 6.1, found {}",
+                other.type_str()
+            )));
+        }
+    };
+    text.parse().map(Some).map_err(de::Error::custom)
+}
+
+/// Scripting `ApiVersion` of each Renoise release line.
+pub const API_VERSIONS: &[(&str, ApiVersion)] = &[
+    ("2.5", ApiVersion::new(1, 0)),
+    ("2.6", ApiVersion::new(2, 0)),
+    ("2.7", ApiVersion::new(3, 0)),
+    ("2.8", ApiVersion::new(4, 0)),
+    ("3.0", ApiVersion::new(4, 0)),
+    ("3.1", ApiVersion::new(5, 0)),
+    ("3.2", ApiVersion::new(6, 0)),
+    ("3.3", ApiVersion::new(6, 0)),
+    ("3.4", ApiVersion::new(6, 1)),
+    ("3.5", ApiVersion::new(6, 2)),
+];
+
+/// `ApiVersion` of a Renoise version such as `3.4` or `3.4.3`.
+pub fn api_version(renoise: &str) -> Option<ApiVersion> {
+    let version = parse_version(renoise.trim_start_matches('V')).ok()?;
+    let line = format!("{}.{}", version.major, version.minor);
+    API_VERSIONS
+        .iter()
+        .find(|(release, _)| *release == line)
+        .map(|(_, api)| *api)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_major_and_minor() {
+        assert_eq!("6".parse(), Ok(ApiVersion::new(6, 0)));
+        assert_eq!("6.1".parse(), Ok(ApiVersion::new(6, 1)));
+        assert!("6.".parse::<ApiVersion>().is_err());
+        assert!("6.x".parse::<ApiVersion>().is_err());
+        assert!("-6".parse::<ApiVersion>().is_err());
+        assert_eq!(ApiVersion::new(6, 0).to_string(), "6");
+        assert_eq!(ApiVersion::new(6, 2).to_string(), "6.2");
+        assert!(ApiVersion::new(6, 1) > ApiVersion::new(6, 0));
+    }
+
+    #[test]
+    fn maps_renoise_versions() {
+        assert_eq!(api_version("3.3.2"), Some(ApiVersion::new(6, 0)));
+        assert_eq!(api_version("3.4"), Some(ApiVersion::new(6, 1)));
+        assert_eq!(api_version("V3.5.0"), Some(ApiVersion::new(6, 2)));
+        assert_eq!(api_version("1.0"), None);
+    }
+}
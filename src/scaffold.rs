@@ -0,0 +1,109 @@
+use crate::git::git;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MANIFEST_TEMPLATE: &str = include_str!("templates/manifest.xml");
+const MAIN_LUA_TEMPLATE: &str = include_str!("templates/main.lua");
+const README_TEMPLATE: &str = include_str!("templates/README.md");
+
+/// A file of a project template, path relative to the project root.
+pub struct TemplateFile {
+    pub path: PathBuf,
+    pub contents: Vec<u8>,
+}
+
+/// The skeleton used when no `--template` is given.
+pub fn builtin() -> Vec<TemplateFile> {
+    [
+        ("manifest.xml", MANIFEST_TEMPLATE),
+        ("main.lua", MAIN_LUA_TEMPLATE),
+        ("README.md", README_TEMPLATE),
+    ]
+    .into_iter()
+    .map(|(path, contents)| TemplateFile {
+        path: PathBuf::from(path),
+        contents: contents.as_bytes().to_vec(),
+    })
+    .collect()
+}
+
+/// Where named templates live: `$RNPLUG_TEMPLATES`, or `rnplug/templates` in the
+/// user's config directory.
+pub fn templates_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("RNPLUG_TEMPLATES") {
+        return Some(PathBuf::from(dir));
+    }
+    let config = if cfg!(target_os = "windows") {
+        PathBuf::from(env::var_os("APPDATA")?)
+    } else if let Some(xdg) = env::var_os("XDG_CONFIG_HOME") {
+        PathBuf::from(xdg)
+    } else {
+        PathBuf::from(env::var_os("HOME")?).join(".config")
+    };
+    Some(config.join("rnplug").join("templates"))
+}
+
+/// Loads a template by name from the templates directory, or clones it when
+/// `spec` looks like a git URL.
+pub fn load(spec: &str) -> Result<Vec<TemplateFile>, Box<dyn std::error::Error>> {
+    if spec == "default" {
+        return Ok(builtin());
+    }
+
+    if is_git_url(spec) {
+        let checkout = env::temp_dir().join(format!("rnplug-template-{}", std::process::id()));
+        if checkout.exists() {
+            fs::remove_dir_all(&checkout)?;
+        }
+        let cwd = env::current_dir()?;
+        let target = checkout.to_string_lossy();
+        let cloned = git(&cwd, &["clone", "--depth", "1", spec, &target])
+            .and_then(|_| read_tree(&checkout, &checkout));
+        let _ = fs::remove_dir_all(&checkout);
+        return cloned;
+    }
+
+    let dir = templates_dir()
+        .map(|d| d.join(spec))
+        .filter(|d| d.is_dir())
+        .ok_or_else(|| format!("template '{spec}' not found"))?;
+    read_tree(&dir, &dir)
+}
+
+fn is_git_url(spec: &str) -> bool {
+    spec.contains("://") || spec.starts_with("git@") || spec.ends_with(".git")
+}
+
+fn read_tree(base: &Path, dir: &Path) -> Result<Vec<TemplateFile>, Box<dyn std::error::Error>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.file_name().is_some_and(|n| n == ".git") {
+            continue;
+        }
+        if path.is_dir() {
+            files.extend(read_tree(base, &path)?);
+        } else {
+            files.push(TemplateFile {
+                path: path.strip_prefix(base)?.to_path_buf(),
+                contents: fs::read(&path)?,
+            });
+        }
+    }
+    Ok(files)
+}
+
+/// Substitutes `{{key}}` placeholders; files that aren't UTF-8 are left alone.
+pub fn render(contents: &[u8], vars: &[(&str, &str)]) -> Vec<u8> {
+    match std::str::from_utf8(contents) {
+        Ok(text) => {
+            let mut out = text.to_string();
+            for (key, value) in vars {
+                out = out.replace(&format!("{{{{{key}}}}}"), value);
+            }
+            out.into_bytes()
+        }
+        Err(_) => contents.to_vec(),
+    }
+}
@@ -0,0 +1,347 @@
+use crate::renoise::ApiVersion;
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
+
+/// Element Renoise reads from the root of a tool manifest.
+pub const ROOT: &str = "RenoiseScriptingTool";
+
+/// The `doc_version` every Renoise release writes and reads.
+pub const DOC_VERSION: u32 = 0;
+
+/// The kind of text a manifest element holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+    Text,
+    /// An [`ApiVersion`](crate::renoise::ApiVersion) such as `6` or `6.1`.
+    ApiVersion,
+    Boolean,
+}
+
+/// Children of the root element Renoise understands, with their value types.
+pub const FIELDS: &[(&str, Value)] = &[
+    ("ApiVersion", Value::ApiVersion),
+    ("Id", Value::Text),
+    ("Name", Value::Text),
+    ("Version", Value::Text),
+    ("Author", Value::Text),
+    ("Category", Value::Text),
+    ("Description", Value::Text),
+    ("Homepage", Value::Text),
+    ("Icon", Value::Text),
+    ("Platform", Value::Text),
+    ("AutoUpgrade", Value::Boolean),
+];
+
+/// Checks element names, nesting and value types of a manifest, returning one
+/// message per violation with its line and column, the element path and, where
+/// there is one, a suggestion.
+pub fn check(contents: &str) -> Vec<String> {
+    let mut lint = Lint {
+        contents,
+        problems: Vec::new(),
+    };
+    let mut reader = Reader::from_str(contents);
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut element_start = 0;
+    let mut seen_root = false;
+    // first position of every root child, to point duplicates back at it
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    loop {
+        let before = reader.buffer_position();
+        let event = match reader.read_event() {
+            Ok(event) => event,
+            Err(e) => {
+                let at = reader.buffer_position();
+                lint.at(at, &path, format!("XML error: {e}"));
+                break;
+            }
+        };
+        match event {
+            Event::Start(e) if path.is_empty() => {
+                let name = check_root(&mut lint, before, &e, seen_root);
+                seen_root = true;
+                path.push(name);
+            }
+            Event::Empty(e) if path.is_empty() => {
+                check_root(&mut lint, before, &e, seen_root);
+                seen_root = true;
+            }
+            Event::Start(e) => {
+                let name = element_name(&e);
+                check_child(&mut lint, before, &path, &name, false);
+                check_duplicate(&mut lint, &mut seen, before, &path, &name);
+                path.push(name);
+                element_start = before;
+                text.clear();
+            }
+            Event::Empty(e) => {
+                let name = element_name(&e);
+                check_child(&mut lint, before, &path, &name, true);
+                check_duplicate(&mut lint, &mut seen, before, &path, &name);
+            }
+            Event::Text(t) => match t.unescape() {
+                Ok(t) => text.push_str(&t),
+                Err(e) => lint.at(before, &path, e.to_string()),
+            },
+            Event::CData(t) => text.push_str(&String::from_utf8_lossy(&t)),
+            Event::End(_) => {
+                if path.len() == 2 {
+                    check_value(&mut lint, element_start, &path, text.trim());
+                }
+                path.pop();
+                text.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if !seen_root {
+        lint.problems.push(format!("no <{ROOT}> root element"));
+    }
+    lint.problems
+}
+
+/// Collects problems, rendering byte offsets as line and column.
+struct Lint<'a> {
+    contents: &'a str,
+    problems: Vec<String>,
+}
+
+impl Lint<'_> {
+    fn at(&mut self, offset: usize, path: &[String], message: String) {
+        let (line, column) = line_column(self.contents, offset);
+        self.problems
+            .push(format!("{line}:{column}: /{}: {message}", path.join("/")));
+    }
+}
+
+/// 1-based line and column (in characters) of byte `offset`.
+pub fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Value type of the known root child `name`.
+pub fn field_type(name: &str) -> Option<Value> {
+    FIELDS
+        .iter()
+        .find(|(field, _)| *field == name)
+        .map(|(_, value)| *value)
+}
+
+/// The known field `name` most likely stands for, e.g. `Apiversion` or `Autor`.
+fn suggest(name: &str) -> Option<&'static str> {
+    FIELDS
+        .iter()
+        .map(|(field, _)| *field)
+        .find(|field| field.eq_ignore_ascii_case(name) || edit_distance(field, name) <= 2)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(current)
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+fn element_name(e: &BytesStart) -> String {
+    String::from_utf8_lossy(e.name().as_ref()).into_owned()
+}
+
+/// Checks the root element's name and attributes, returning its name.
+fn check_root(lint: &mut Lint, offset: usize, e: &BytesStart, seen_root: bool) -> String {
+    let name = element_name(e);
+    let path = [name.clone()];
+    if seen_root {
+        lint.at(
+            offset,
+            &path,
+            "only one root element is allowed".to_string(),
+        );
+    } else if name != ROOT {
+        lint.at(offset, &path, format!("root element must be <{ROOT}>"));
+    }
+    for attr in e.attributes() {
+        let attr = match attr {
+            Ok(attr) => attr,
+            Err(err) => {
+                lint.at(offset, &path, format!("invalid attribute: {err}"));
+                continue;
+            }
+        };
+        let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+        let value = String::from_utf8_lossy(&attr.value).into_owned();
+        let attr_path = [format!("{name}/@{key}")];
+        match key.as_str() {
+            "doc_version" if value.parse::<u32>().is_err() => lint.at(
+                offset,
+                &attr_path,
+                format!("expected an integer, found '{value}'; use doc_version=\"{DOC_VERSION}\""),
+            ),
+            "doc_version" if value != DOC_VERSION.to_string() => lint.at(
+                offset,
+                &attr_path,
+                format!("unsupported value {value}, Renoise reads doc_version {DOC_VERSION}"),
+            ),
+            "doc_version" => {}
+            _ => lint.at(
+                offset,
+                &attr_path,
+                "unknown attribute; only doc_version is allowed".to_string(),
+            ),
+        }
+    }
+    name
+}
+
+/// Checks an element below the root: only known fields, and no nesting.
+fn check_child(lint: &mut Lint, offset: usize, path: &[String], name: &str, empty: bool) {
+    let mut child = path.to_vec();
+    child.push(name.to_string());
+    if path.len() > 1 {
+        let parent = &path[path.len() - 1];
+        lint.at(
+            offset,
+            &child,
+            format!("<{parent}> may only contain text; escape `<` as &lt; or use CDATA"),
+        );
+        return;
+    }
+    match field_type(name) {
+        None => {
+            let hint = match suggest(name) {
+                Some(field) => format!("unknown element, did you mean <{field}>?"),
+                None => "unknown element".to_string(),
+            };
+            lint.at(offset, &child, hint);
+        }
+        Some(Value::ApiVersion | Value::Boolean) if empty => {
+            lint.at(offset, &child, "value is empty".to_string());
+        }
+        Some(_) => {}
+    }
+}
+
+/// Flags a root child that appears more than once; serde would reject or
+/// silently pick one of them.
+fn check_duplicate(
+    lint: &mut Lint,
+    seen: &mut HashMap<String, usize>,
+    offset: usize,
+    path: &[String],
+    name: &str,
+) {
+    if path.len() != 1 {
+        return;
+    }
+    match seen.get(name) {
+        Some(first) => {
+            let (line, column) = line_column(lint.contents, *first);
+            let child = [path[0].clone(), name.to_string()];
+            lint.at(
+                offset,
+                &child,
+                format!("duplicate element, first defined at {line}:{column}; keep only one"),
+            );
+        }
+        None => {
+            seen.insert(name.to_string(), offset);
+        }
+    }
+}
+
+fn check_value(lint: &mut Lint, offset: usize, path: &[String], value: &str) {
+    let suggestion = match field_type(&path[1]) {
+        Some(Value::ApiVersion) if value.parse::<ApiVersion>().is_err() => {
+            format!(
+                "expected a version such as 6 or 6.1, found '{value}', e.g. <{0}>6.1</{0}>",
+                path[1]
+            )
+        }
+        Some(Value::Boolean) if !matches!(value, "true" | "false") => {
+            format!("expected true or false, found '{value}'")
+        }
+        _ => return,
+    };
+    lint.at(offset, path, suggestion);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(children: &str) -> String {
+        format!("<?xml version=\"1.0\"?>\n<{ROOT} doc_version=\"0\">\n{children}</{ROOT}>\n")
+    }
+
+    #[test]
+    fn reports_line_and_column() {
+        let contents = manifest(
+            "  <Id>com.example.Hi</Id>\n  <Autor>me</Autor>\n  <ApiVersion>six</ApiVersion>\n  <Name>Grüße</Name><Colour>red</Colour>\n",
+        );
+        assert_eq!(
+            check(&contents),
+            [
+                "4:3: /RenoiseScriptingTool/Autor: unknown element, did you mean <Author>?",
+                "5:3: /RenoiseScriptingTool/ApiVersion: expected a version such as 6 or 6.1, \
+                 found 'six', e.g. <ApiVersion>6.1</ApiVersion>",
+                // columns count characters, not bytes
+                "6:21: /RenoiseScriptingTool/Colour: unknown element",
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_lines_and_characters() {
+        for (offset, expected) in [
+            (0, (1, 1)),
+            (3, (1, 4)),
+            (4, (2, 1)),
+            (7, (2, 3)),
+            (99, (3, 1)),
+        ] {
+            assert_eq!(line_column("abc\nüx\n", offset), expected, "{offset}");
+        }
+    }
+
+    #[test]
+    fn points_duplicates_at_the_first_definition() {
+        let contents = manifest(
+            "  <Id>com.example.Hi</Id>\n  <Name>Hi</Name>\n  <Id>com.example.Other</Id>\n  <Name/>\n",
+        );
+        assert_eq!(
+            check(&contents),
+            [
+                "5:3: /RenoiseScriptingTool/Id: duplicate element, first defined at 3:3; keep only one",
+                "6:3: /RenoiseScriptingTool/Name: duplicate element, first defined at 4:3; keep only one",
+            ]
+        );
+    }
+
+    #[test]
+    fn only_checks_duplicates_below_the_root() {
+        let contents = manifest("  <Description>a<b/><b/></Description>\n");
+        let nested = "/RenoiseScriptingTool/Description/b: <Description> may only contain text; \
+                      escape `<` as &lt; or use CDATA";
+        assert_eq!(
+            check(&contents),
+            [format!("3:17: {nested}"), format!("3:21: {nested}")]
+        );
+    }
+}
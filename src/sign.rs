@@ -0,0 +1,99 @@
+use crate::config::{Signing, SigningTool};
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Detached signature of `archive` made by `tool`, next to it.
+pub fn signature_path(archive: &Path, tool: SigningTool) -> PathBuf {
+    let mut path = archive.as_os_str().to_owned();
+    path.push(match tool {
+        SigningTool::Minisign => ".minisig",
+        SigningTool::Gpg => ".asc",
+    });
+    PathBuf::from(path)
+}
+
+/// Signs `archive` with the configured tool and key, returning the signature's path.
+pub fn sign(signing: &Signing, archive: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let tool = signing.tool.unwrap_or_default();
+    let signature = signature_path(archive, tool);
+    let mut args: Vec<OsString> = Vec::new();
+    match tool {
+        SigningTool::Minisign => {
+            args.push("-S".into());
+            if let Some(key) = &signing.key {
+                args.extend(["-s".into(), key.into()]);
+            }
+            args.extend(["-m".into(), archive.into()]);
+        }
+        SigningTool::Gpg => {
+            args.extend(["--batch", "--yes", "--armor", "--detach-sign"].map(OsString::from));
+            if let Some(key) = &signing.key {
+                args.extend(["--local-user".into(), key.into()]);
+            }
+            args.extend(["--output".into(), signature.clone().into(), archive.into()]);
+        }
+    }
+    run(tool, &args)?;
+    Ok(signature)
+}
+
+/// Checks the detached signature next to `archive`, made by the configured
+/// tool or whichever signature file exists.
+pub fn verify(signing: &Signing, archive: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let tool = match signing.tool {
+        Some(tool) => tool,
+        None => [SigningTool::Minisign, SigningTool::Gpg]
+            .into_iter()
+            .find(|tool| signature_path(archive, *tool).is_file())
+            .ok_or_else(|| format!("{} has no .minisig or .asc signature", archive.display()))?,
+    };
+    let signature = signature_path(archive, tool);
+    if !signature.is_file() {
+        return Err(format!("{} not found", signature.display()).into());
+    }
+    let mut args: Vec<OsString> = Vec::new();
+    match tool {
+        SigningTool::Minisign => {
+            args.push("-V".into());
+            match &signing.public_key {
+                // a key file, or the key itself as printed by `minisign -G`
+                Some(key) if Path::new(key).is_file() => args.extend(["-p".into(), key.into()]),
+                Some(key) => args.extend(["-P".into(), key.into()]),
+                None => {}
+            }
+            args.extend(["-m".into(), archive.into()]);
+        }
+        SigningTool::Gpg => {
+            args.extend(["--verify".into(), signature.clone().into(), archive.into()]);
+        }
+    }
+    run(tool, &args).map_err(|e| {
+        format!(
+            "{} does not match {}: {e}",
+            archive.display(),
+            signature.display()
+        )
+    })?;
+    Ok(signature)
+}
+
+fn run(tool: SigningTool, args: &[OsString]) -> Result<(), Box<dyn std::error::Error>> {
+    let program = match tool {
+        SigningTool::Minisign => "minisign",
+        SigningTool::Gpg => "gpg",
+    };
+    // inherits the terminal, so either tool can ask for the key's password
+    let status = Command::new(program).args(args).status().map_err(|e| {
+        if e.kind() == io::ErrorKind::NotFound {
+            format!("{program} not found, install it or change signing.tool in rnplug.toml")
+        } else {
+            format!("cannot run {program}: {e}")
+        }
+    })?;
+    if !status.success() {
+        return Err(format!("{program} failed ({status})").into());
+    }
+    Ok(())
+}
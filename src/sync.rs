@@ -0,0 +1,131 @@
+use crate::config::VersionSync;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A project file rewritten to carry the new version.
+pub struct SyncedFile {
+    /// Path relative to the project root.
+    pub path: PathBuf,
+    pub original: String,
+    pub updated: String,
+}
+
+/// Applies every `[[version_sync]]` rule, returning the files whose contents change.
+pub fn sync_versions(
+    root: &Path,
+    rules: &[VersionSync],
+    version: &str,
+) -> Result<Vec<SyncedFile>, Box<dyn std::error::Error>> {
+    let mut synced: Vec<SyncedFile> = Vec::new();
+    for rule in rules {
+        // several rules may target the same file
+        let index = match synced.iter().position(|f| f.path == rule.file) {
+            Some(i) => i,
+            None => {
+                let original = fs::read_to_string(root.join(&rule.file)).map_err(|e| {
+                    format!("version_sync: cannot read {}: {e}", rule.file.display())
+                })?;
+                synced.push(SyncedFile {
+                    path: rule.file.clone(),
+                    updated: original.clone(),
+                    original,
+                });
+                synced.len() - 1
+            }
+        };
+        let file = &mut synced[index];
+        file.updated =
+            replace_placeholder(&file.updated, &rule.pattern, version).ok_or_else(|| {
+                format!(
+                    "version_sync: pattern `{}` not found in {}",
+                    rule.pattern,
+                    rule.file.display()
+                )
+            })?;
+    }
+
+    synced.retain(|f| f.updated != f.original);
+    Ok(synced)
+}
+
+/// Replaces whatever stands where `{version}` is in the first place `pattern`
+/// matches, e.g. `local TOOL_VERSION = "{version}"`.
+fn replace_placeholder(contents: &str, pattern: &str, version: &str) -> Option<String> {
+    let (prefix, suffix) = pattern.split_once("{version}")?;
+    let mut search_from = 0;
+    while let Some(found) = contents[search_from..].find(prefix) {
+        let start = search_from + found + prefix.len();
+        let line_end = contents[start..]
+            .find('\n')
+            .map_or(contents.len(), |i| start + i);
+        let end = if suffix.is_empty() {
+            Some(line_end)
+        } else {
+            contents[start..line_end].find(suffix).map(|i| start + i)
+        };
+        if let Some(end) = end {
+            return Some(format!(
+                "{}{version}{}",
+                &contents[..start],
+                &contents[end..]
+            ));
+        }
+        // on to the next match, one character on so an empty prefix can't stall
+        let Some(next) = contents[search_from + found..].chars().next() else {
+            break;
+        };
+        search_from += found + next.len_utf8();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_the_first_full_match() {
+        let cases = [
+            // match on a later line
+            (
+                "-- VERSION = \"old\"\nlocal x = 1\nlocal VERSION = \"0.9\"\n",
+                "local VERSION = \"{version}\"",
+                Some("-- VERSION = \"old\"\nlocal x = 1\nlocal VERSION = \"1.0\"\n"),
+            ),
+            // the suffix of the first prefix match is on the next line
+            (
+                "version = \nfoo\"\nversion = \"0.9\"\n",
+                "version = \"{version}\"",
+                Some("version = \nfoo\"\nversion = \"1.0\"\n"),
+            ),
+            // no suffix: up to the end of the line
+            ("v: 0.9\nrest\n", "v: {version}", Some("v: 1.0\nrest\n")),
+            // empty prefix: the first line with the suffix
+            ("abc\n0.9 (x)\n", "{version} (x)", Some("abc\n1.0 (x)\n")),
+            ("abc\nd\u{e9}f\n", "{version} (x)", None),
+            ("local VERSION = 1\n", "TOOL_VERSION = \"{version}\"", None),
+            ("local VERSION = 1\n", "no placeholder", None),
+        ];
+        for (contents, pattern, expected) in cases {
+            assert_eq!(
+                replace_placeholder(contents, pattern, "1.0").as_deref(),
+                expected,
+                "{pattern}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_patterns_without_a_prefix() {
+        let parse = |pattern: &str| {
+            toml::from_str::<VersionSync>(&format!("file = \"a.lua\"\npattern = '{pattern}'"))
+        };
+        assert!(parse("V = \"{version}\"").is_ok());
+        let error = parse("{version}\"").unwrap_err().to_string();
+        assert!(
+            error.contains("needs some text before {version}"),
+            "{error}"
+        );
+        assert!(parse("V = 1").is_err());
+    }
+}
@@ -0,0 +1,12 @@
+--------------------------------------------------------------------------------
+-- {{name}}
+--------------------------------------------------------------------------------
+
+local function show_dialog()
+  renoise.app():show_status("{{name}} says hello")
+end
+
+renoise.tool():add_menu_entry {
+  name = "Main Menu:Tools:{{name}}...",
+  invoke = show_dialog
+}
@@ -0,0 +1,211 @@
+use crate::archive::SourceFile;
+use crate::config::{LineEnding, Settings};
+use full_moon::LuaVersion;
+use full_moon::tokenizer::{Lexer, LexerResult, Token, TokenType};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A change made to a file on its way into the archive. The file on disk is
+/// never touched. Files are transformed on several threads at once.
+pub trait Transform: Sync {
+    fn applies_to(&self, source: &SourceFile) -> bool;
+    fn apply(&self, source: &SourceFile, contents: Vec<u8>) -> Result<Vec<u8>, String>;
+}
+
+/// Transforms run in order, each on the output of the previous one.
+#[derive(Default)]
+pub struct Pipeline<'a> {
+    transforms: Vec<Box<dyn Transform + 'a>>,
+}
+
+impl<'a> Pipeline<'a> {
+    /// The transforms configured in `[transforms]`, after `overrides` (files
+    /// rewritten by version sync and placeholders, relative to `root`).
+    /// Debug blocks are only stripped from, and Lua only minified in, `release`
+    /// builds.
+    pub fn for_build(
+        root: &'a Path,
+        settings: &Settings,
+        overrides: &'a BTreeMap<PathBuf, Vec<u8>>,
+        release: bool,
+    ) -> Self {
+        let configured = settings.transforms.clone().unwrap_or_default();
+        let mut pipeline = Pipeline::default().with(Replace {
+            root,
+            contents: overrides,
+        });
+        if release && configured.strip_debug == Some(true) {
+            pipeline = pipeline.with(StripDebug);
+        }
+        if release && configured.minify == Some(true) {
+            pipeline = pipeline.with(Minify);
+        }
+        if let Some(ending) = configured.line_endings {
+            pipeline = pipeline.with(LineEndings(ending));
+        }
+        pipeline
+    }
+
+    pub fn with(mut self, transform: impl Transform + 'a) -> Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    /// Whether any transform changes `source`, so it can't be copied as is.
+    pub fn applies_to(&self, source: &SourceFile) -> bool {
+        self.transforms.iter().any(|t| t.applies_to(source))
+    }
+
+    pub fn apply(&self, source: &SourceFile, mut contents: Vec<u8>) -> Result<Vec<u8>, String> {
+        for transform in self.transforms.iter().filter(|t| t.applies_to(source)) {
+            contents = transform
+                .apply(source, contents)
+                .map_err(|e| format!("{}: {e}", source.name))?;
+        }
+        Ok(contents)
+    }
+}
+
+/// Packages the given contents instead of what's on disk.
+pub struct Replace<'a> {
+    root: &'a Path,
+    contents: &'a BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl Replace<'_> {
+    fn get(&self, source: &SourceFile) -> Option<&Vec<u8>> {
+        let relative = source.path.strip_prefix(self.root).ok()?;
+        self.contents.get(relative)
+    }
+}
+
+impl Transform for Replace<'_> {
+    fn applies_to(&self, source: &SourceFile) -> bool {
+        self.get(source).is_some()
+    }
+
+    fn apply(&self, source: &SourceFile, contents: Vec<u8>) -> Result<Vec<u8>, String> {
+        Ok(self.get(source).cloned().unwrap_or(contents))
+    }
+}
+
+/// Drops the lines from `--[[DEBUG]]` to `--[[/DEBUG]]` out of Lua files. Both
+/// markers are complete comments, so the code between them runs during
+/// development.
+pub struct StripDebug;
+
+impl Transform for StripDebug {
+    fn applies_to(&self, source: &SourceFile) -> bool {
+        source.name.ends_with(".lua")
+    }
+
+    fn apply(&self, _source: &SourceFile, contents: Vec<u8>) -> Result<Vec<u8>, String> {
+        let text = String::from_utf8(contents).map_err(|_| "not UTF-8".to_string())?;
+        let mut stripped = String::with_capacity(text.len());
+        let mut opened = None;
+        for (number, line) in text.split_inclusive('\n').enumerate() {
+            let marker = line.trim();
+            if opened.is_none() && marker.starts_with("--[[DEBUG]]") {
+                opened = Some(number + 1);
+            } else if opened.is_some() && marker.starts_with("--[[/DEBUG]]") {
+                opened = None;
+            } else if opened.is_none() {
+                stripped.push_str(line);
+            }
+        }
+        if let Some(line) = opened {
+            return Err(format!(
+                "--[[DEBUG]] on line {line} is never closed with --[[/DEBUG]]"
+            ));
+        }
+        Ok(stripped.into_bytes())
+    }
+}
+
+/// Strips comments from Lua files and collapses whitespace between tokens to a
+/// single space, or to a line break where there was one so statements stay
+/// apart as they were.
+pub struct Minify;
+
+impl Minify {
+    /// Whether `a` followed by `b` needs a space to stay two tokens, which is
+    /// only certain not to when either is a bracket or separator.
+    fn needs_space(a: &Token, b: &Token) -> bool {
+        let separator = |token: &Token| {
+            matches!(token.token_type(), TokenType::Symbol { .. })
+                && matches!(
+                    token.to_string().as_str(),
+                    "(" | ")" | "{" | "}" | "," | ";"
+                )
+        };
+        !separator(a) && !separator(b)
+    }
+}
+
+impl Transform for Minify {
+    fn applies_to(&self, source: &SourceFile) -> bool {
+        source.name.ends_with(".lua")
+    }
+
+    fn apply(&self, _source: &SourceFile, contents: Vec<u8>) -> Result<Vec<u8>, String> {
+        let text = String::from_utf8(contents).map_err(|_| "not UTF-8".to_string())?;
+        let LexerResult::Ok(tokens) = Lexer::new(&text, LuaVersion::lua51()).collect() else {
+            return Err("cannot be minified, it isn't valid Lua".to_string());
+        };
+        let mut minified = String::with_capacity(text.len());
+        // whether whitespace or comments came since the last token, and a line break
+        let mut gap: Option<bool> = None;
+        let mut last: Option<&Token> = None;
+        for token in &tokens {
+            match token.token_type() {
+                TokenType::Whitespace { .. }
+                | TokenType::SingleLineComment { .. }
+                | TokenType::MultiLineComment { .. } => {
+                    let newline = token.to_string().contains('\n');
+                    gap = Some(gap.unwrap_or(false) || newline);
+                }
+                TokenType::Eof => {}
+                _ => {
+                    if let (Some(newline), Some(last)) = (gap, last) {
+                        if newline {
+                            minified.push('\n');
+                        } else if Self::needs_space(last, token) {
+                            minified.push(' ');
+                        }
+                    }
+                    minified.push_str(&token.to_string());
+                    last = Some(token);
+                    gap = None;
+                }
+            }
+        }
+        minified.push('\n');
+        Ok(minified.into_bytes())
+    }
+}
+
+/// Rewrites the line endings of text files.
+pub struct LineEndings(pub LineEnding);
+
+impl Transform for LineEndings {
+    fn applies_to(&self, source: &SourceFile) -> bool {
+        let extension = source
+            .name
+            .rsplit_once('.')
+            .map(|(_, e)| e.to_ascii_lowercase());
+        matches!(
+            extension.as_deref(),
+            Some("lua" | "xml" | "md" | "txt" | "json")
+        )
+    }
+
+    fn apply(&self, _source: &SourceFile, contents: Vec<u8>) -> Result<Vec<u8>, String> {
+        let text = String::from_utf8(contents).map_err(|_| "not UTF-8".to_string())?;
+        let text = text.replace("\r\n", "\n");
+        Ok(match self.0 {
+            LineEnding::Lf => text,
+            LineEnding::Crlf => text.replace('\n', "\r\n"),
+        }
+        .into_bytes())
+    }
+}
@@ -0,0 +1,201 @@
+use crate::annotations;
+use crate::api;
+use crate::archive::{collect_sources, license_path};
+use crate::config::Settings;
+use crate::manifest::{get_field, read_file, read_manifest};
+use crate::renoise::ApiVersion;
+use crate::schema;
+use crate::version::parse_version;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Checks the manifest and project layout, returning every problem found.
+/// `main_lua` is the file packaged as main.lua, see [`Settings::main_lua`].
+pub fn validate(manifest_path: &Path, main_lua: &Path) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    match read_file(manifest_path) {
+        Ok((contents, _)) => {
+            let violations = schema::check(&contents);
+            let well_formed = violations.is_empty();
+            problems.extend(violations);
+            match read_manifest(&contents) {
+                Ok(manifest) => {
+                    let required = [
+                        ("Id", manifest.id.is_some()),
+                        ("Version", manifest.version.is_some()),
+                        ("ApiVersion", manifest.api_version.is_some()),
+                        ("Name", manifest.name.is_some()),
+                    ];
+                    for (field, present) in required {
+                        if !present {
+                            problems.push(format!("missing required field `{field}`"));
+                        }
+                    }
+                    if manifest
+                        .name
+                        .as_deref()
+                        .is_some_and(|n| n.trim().is_empty())
+                    {
+                        problems.push("`Name` is empty".to_string());
+                    }
+                    if let Ok(lua) = fs::read_to_string(main_lua) {
+                        problems.extend(annotation_mismatches(&lua, &contents));
+                    }
+                    if let Some(id) = &manifest.id
+                        && let Err(e) = check_id(id)
+                    {
+                        problems.push(e);
+                    }
+                    if let Some(version) = &manifest.version
+                        && let Err(e) = parse_version(version)
+                    {
+                        problems.push(format!("invalid version '{version}': {e}"));
+                    }
+                }
+                // the schema check already said what is wrong, with element paths
+                Err(_) if !well_formed => {}
+                Err(e) => {
+                    problems.push(format!("failed to parse {}: {e}", manifest_path.display()))
+                }
+            }
+        }
+        Err(e) => problems.push(format!("cannot read {}: {e}", manifest_path.display())),
+    }
+
+    if !main_lua.is_file() {
+        problems.push(format!("{} not found", main_lua.display()));
+    }
+
+    problems
+}
+
+/// Longest Name the Renoise tool browser shows without cutting it off.
+pub const NAME_MAX: usize = 40;
+/// Longest Description that still reads well in the tool browser.
+pub const DESCRIPTION_MAX: usize = 300;
+
+/// Cosmetic problems with Name and Description that Renoise tolerates. The
+/// raw element text is used, the deserialized fields come trimmed.
+pub fn field_warnings(manifest: &str) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if let Ok(Some(name)) = get_field(manifest, "Name") {
+        if name.trim() != name {
+            warnings.push(format!("Name '{name}' has leading or trailing whitespace"));
+        }
+        let length = name.trim().chars().count();
+        if length > NAME_MAX {
+            warnings.push(format!(
+                "Name is {length} characters long, the tool browser shows about {NAME_MAX}"
+            ));
+        }
+    }
+    if let Ok(Some(description)) = get_field(manifest, "Description") {
+        let length = description.trim().chars().count();
+        if length == 0 {
+            warnings.push("Description is empty".to_string());
+        } else if length > DESCRIPTION_MAX {
+            warnings.push(format!(
+                "Description is {length} characters long, keep it under {DESCRIPTION_MAX} for the tool browser"
+            ));
+        }
+    }
+    warnings
+}
+
+/// main.lua `-- @tag` annotations that disagree with the manifest.
+fn annotation_mismatches(lua: &str, manifest: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (name, annotated) in annotations::parse(lua) {
+        let declared = get_field(manifest, name).ok().flatten();
+        if declared.as_deref().map(str::trim) != Some(annotated.as_str()) {
+            problems.push(format!(
+                "main.lua says @{} '{annotated}' but the manifest has {}",
+                name.to_ascii_lowercase(),
+                declared.map_or("none".to_string(), |d| format!("'{d}'"))
+            ));
+        }
+    }
+    problems
+}
+
+/// Checks that `id` follows the `tld.domain.ToolName` convention Renoise relies
+/// on for the install folder name.
+pub fn check_id(id: &str) -> Result<(), String> {
+    let parts: Vec<&str> = id.split('.').collect();
+    let well_formed = parts.len() >= 3
+        && parts.iter().all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        });
+    if well_formed {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid Id '{id}': expected a reverse-domain identifier like com.example.MyTool, \
+             at least three dot-separated parts of letters, digits, '_' or '-'"
+        ))
+    }
+}
+
+/// A tool marked `public` in rnplug.toml without a license file.
+pub fn license_warning(root: &Path, settings: &Settings) -> Option<String> {
+    (settings.public == Some(true) && license_path(root).is_none())
+        .then(|| "the tool is public but has no LICENSE, LICENSE.md or COPYING file".to_string())
+}
+
+/// Uses of Renoise API parts newer than the manifest's `api_version` in the
+/// packaged Lua files, see [`api::newer_api_uses`].
+pub fn api_warnings(
+    root: &Path,
+    settings: &Settings,
+    api_version: ApiVersion,
+) -> io::Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    for source in collect_sources(root, settings, false)? {
+        if source.name.ends_with(".lua")
+            && let Ok(code) = fs::read_to_string(&source.path)
+        {
+            warnings.extend(api::newer_api_uses(&source.name, &code, api_version));
+        }
+    }
+    Ok(warnings)
+}
+
+/// Naming mismatches that usually come from copying another project: Renoise
+/// installs a tool as `<Id>.xrnx`, so the project folder and the archives in
+/// the release directory, if given, are expected to carry the Id as well.
+pub fn naming_warnings(root: &Path, tool_id: &str, release_dir: Option<&Path>) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if let Ok(dir) = root.canonicalize()
+        && let Some(name) = dir.file_name().and_then(|n| n.to_str())
+        && name != tool_id
+        && name.strip_suffix(".xrnx") != Some(tool_id)
+    {
+        warnings.push(format!(
+            "project folder '{name}' does not match the Id, expected {tool_id} or {tool_id}.xrnx"
+        ));
+    }
+
+    if let Some(release_dir) = release_dir
+        && let Ok(entries) = fs::read_dir(release_dir)
+    {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(stem) = name.strip_suffix(".xrnx") else {
+                continue;
+            };
+            if stem != tool_id && !stem.starts_with(&format!("{tool_id}-")) {
+                warnings.push(format!(
+                    "{} does not belong to {tool_id}",
+                    release_dir.join(&name).display()
+                ));
+            }
+        }
+    }
+    warnings
+}
@@ -0,0 +1,374 @@
+use semver::{Prerelease, Version};
+use std::fmt;
+use time::Date;
+
+/// A version as written in a manifest. Renoise tools often use `3.1` rather than
+/// `3.1.0`, so the number of numeric components is remembered and the same style
+/// is written back, growing only when a lower component becomes non-zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolVersion {
+    pub version: Version,
+    pub components: usize,
+}
+
+impl ToolVersion {
+    pub fn parse(input: &str) -> Result<Self, semver::Error> {
+        let base = input.split(['-', '+']).next().unwrap_or(input);
+        let components = base
+            .split('.')
+            .filter(|s| !s.is_empty())
+            .count()
+            .clamp(1, 3);
+        Ok(ToolVersion {
+            version: parse_version(input)?,
+            components,
+        })
+    }
+}
+
+impl fmt::Display for ToolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = &self.version;
+        let needed = if v.patch != 0 {
+            3
+        } else if v.minor != 0 {
+            2
+        } else {
+            1
+        };
+        match self.components.max(needed) {
+            1 => write!(f, "{}", v.major)?,
+            2 => write!(f, "{}.{}", v.major, v.minor)?,
+            _ => write!(f, "{}.{}.{}", v.major, v.minor, v.patch)?,
+        }
+        if !v.pre.is_empty() {
+            write!(f, "-{}", v.pre)?;
+        }
+        if !v.build.is_empty() {
+            write!(f, "+{}", v.build)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_version(input: &str) -> Result<Version, semver::Error> {
+    match Version::parse(input) {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            let (base, rest) = match input.find(['-', '+']) {
+                Some(idx) => (&input[..idx], Some(&input[idx..])),
+                None => (input, None),
+            };
+            let count = base.split('.').filter(|s| !s.is_empty()).count();
+            let adjusted = match count {
+                1 => format!("{}.0.0", base.trim_end_matches('.')),
+                2 => format!("{}.0", base.trim_end_matches('.')),
+                _ => return Err(e),
+            };
+            let candidate = match rest {
+                Some(r) => format!("{}{}", adjusted, r),
+                None => adjusted,
+            };
+            Version::parse(&candidate)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BumpKind {
+    Major,
+    Minor,
+    Patch,
+}
+
+pub fn bump(version: &mut Version, kind: BumpKind) {
+    // lower components are only reset for plain versions, not pre-release/build ones
+    let reset = version.build.is_empty() && version.pre.is_empty();
+    increment(version, kind, reset);
+}
+
+/// Like [`bump`] but never resets lower components, `1.3.2` -> `1.4.2`.
+pub fn bump_keep_lower(version: &mut Version, kind: BumpKind) {
+    increment(version, kind, false);
+}
+
+fn increment(version: &mut Version, kind: BumpKind, reset: bool) {
+    match kind {
+        BumpKind::Major => {
+            version.major += 1;
+            if reset {
+                version.minor = 0;
+                version.patch = 0;
+            }
+        }
+        BumpKind::Minor => {
+            version.minor += 1;
+            if reset {
+                version.patch = 0;
+            }
+        }
+        BumpKind::Patch => version.patch += 1,
+    }
+}
+
+/// Moves `version` onto the `channel` pre-release line, e.g. `1.4.0-beta.1` ->
+/// `1.4.0-beta.2`, or `1.3.0` -> `1.4.0-beta.1` when a core bump is needed.
+/// `kind` forces a core bump, otherwise a `default` one only happens when just
+/// switching channels would not produce a newer version.
+pub fn bump_pre(
+    version: &mut Version,
+    channel: &str,
+    kind: Option<BumpKind>,
+    default: BumpKind,
+) -> Result<(), semver::Error> {
+    let current = version.clone();
+    let (current_channel, number) = split_pre(&current.pre);
+
+    if kind.is_none() && current_channel == Some(channel) {
+        version.pre = Prerelease::new(&format!("{channel}.{}", number + 1))?;
+        return Ok(());
+    }
+
+    let first = Prerelease::new(&format!("{channel}.1"))?;
+    version.pre = Prerelease::EMPTY;
+    version.build = semver::BuildMetadata::EMPTY;
+    if let Some(kind) = kind {
+        bump(version, kind);
+    } else if current.pre.is_empty() {
+        bump(version, default);
+    }
+    version.pre = first.clone();
+
+    if *version <= current {
+        // e.g. rc -> alpha on the same core version, move on to the next one
+        version.pre = Prerelease::EMPTY;
+        bump(version, kind.unwrap_or(default));
+        version.pre = first;
+    }
+    Ok(())
+}
+
+/// Moves a pre-release one step along `channels`, e.g. `1.4.0-alpha.3` ->
+/// `1.4.0-beta.1`, and from the last channel to the plain `1.4.0`.
+pub fn promote(version: &mut Version, channels: &[String]) -> Result<(), String> {
+    let (Some(channel), _) = split_pre(&version.pre) else {
+        return Err(format!(
+            "{version} is not a pre-release, nothing to promote"
+        ));
+    };
+    let Some(index) = channels.iter().position(|c| c == channel) else {
+        return Err(format!(
+            "pre-release channel '{channel}' is not one of {}",
+            channels.join(", ")
+        ));
+    };
+    version.pre = match channels.get(index + 1) {
+        Some(next) => Prerelease::new(&format!("{next}.1"))
+            .map_err(|e| format!("invalid pre-release channel '{next}': {e}"))?,
+        None => Prerelease::EMPTY,
+    };
+    Ok(())
+}
+
+/// Splits `beta.3` into its channel and number; a missing number counts as 0.
+pub fn split_pre(pre: &Prerelease) -> (Option<&str>, u64) {
+    if pre.is_empty() {
+        return (None, 0);
+    }
+    match pre.as_str().split_once('.') {
+        Some((channel, n)) => (Some(channel), n.parse().unwrap_or(0)),
+        None => (Some(pre.as_str()), 0),
+    }
+}
+
+/// Calendar versioning, `YYYY.M.patch`: the patch counts up within a month and
+/// starts over at 0 when the month changes.
+pub fn bump_calver(version: &mut Version, today: Date) {
+    let year = today.year() as u64;
+    let month = u8::from(today.month()) as u64;
+    if version.major == year && version.minor == month {
+        version.patch += 1;
+    } else {
+        version.major = year;
+        version.minor = month;
+        version.patch = 0;
+    }
+    version.pre = Prerelease::EMPTY;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `input` parsed, changed by `change` and written back as a manifest would be.
+    fn changed(input: &str, change: impl FnOnce(&mut Version)) -> String {
+        let mut version = ToolVersion::parse(input).unwrap();
+        change(&mut version.version);
+        version.to_string()
+    }
+
+    #[test]
+    fn bumps_reset_lower_components() {
+        use BumpKind::{Major, Minor, Patch};
+        for (input, kind, expected) in [
+            ("1.2.3", Major, "2.0.0"),
+            ("1.2.3", Minor, "1.3.0"),
+            ("1.2.3", Patch, "1.2.4"),
+            ("1.2", Major, "2.0"),
+            ("1.2", Minor, "1.3"),
+            ("1.2", Patch, "1.2.1"),
+            ("1", Major, "2"),
+            ("1", Minor, "1.1"),
+            ("0.9", Minor, "0.10"),
+            // pre-releases keep their lower components
+            ("1.2.3-beta.1", Major, "2.2.3-beta.1"),
+        ] {
+            assert_eq!(
+                changed(input, |v| bump(v, kind)),
+                expected,
+                "{input} {kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn bumps_pre_releases() {
+        use BumpKind::{Minor, Patch};
+        for (input, channel, kind, expected) in [
+            ("1.3.0", "beta", None, "1.4.0-beta.1"),
+            ("1.3", "beta", None, "1.4-beta.1"),
+            ("1.4.0-beta.1", "beta", None, "1.4.0-beta.2"),
+            ("1.4.0-beta.9", "beta", None, "1.4.0-beta.10"),
+            ("1.4.0-beta", "beta", None, "1.4.0-beta.1"),
+            // on to a later channel of the same version
+            ("1.4.0-alpha.3", "beta", None, "1.4.0-beta.1"),
+            ("1.4.0-beta.2", "rc", None, "1.4.0-rc.1"),
+            // back to an earlier channel needs the next version
+            ("1.4.0-rc.1", "alpha", None, "1.5.0-alpha.1"),
+            ("1.4.0-rc.1", "beta", None, "1.5.0-beta.1"),
+            // an explicit bump always moves the core version
+            ("1.4.0-beta.2", "beta", Some(Patch), "1.4.1-beta.1"),
+            ("1.3.0", "alpha", Some(Patch), "1.3.1-alpha.1"),
+        ] {
+            assert_eq!(
+                changed(input, |v| bump_pre(v, channel, kind, Minor).unwrap()),
+                expected,
+                "{input} {channel} {kind:?}"
+            );
+        }
+        assert!(bump_pre(&mut Version::new(1, 0, 0), "no good", None, Minor).is_err());
+    }
+
+    #[test]
+    fn splits_pre_release_numbers() {
+        for (pre, expected) in [
+            ("", (None, 0)),
+            ("beta", (Some("beta"), 0)),
+            ("beta.3", (Some("beta"), 3)),
+            ("rc.x", (Some("rc"), 0)),
+        ] {
+            assert_eq!(split_pre(&Prerelease::new(pre).unwrap()), expected, "{pre}");
+        }
+    }
+
+    #[test]
+    fn keeps_the_precision_of_versions() {
+        for (input, components, written) in [
+            ("3", 1, "3"),
+            ("3.1", 2, "3.1"),
+            ("3.0", 2, "3.0"),
+            ("3.1.0", 3, "3.1.0"),
+            ("3.1-beta.2", 2, "3.1-beta.2"),
+            ("3.1+abc", 2, "3.1+abc"),
+        ] {
+            let version = ToolVersion::parse(input).unwrap();
+            assert_eq!(version.components, components, "{input}");
+            assert_eq!(version.to_string(), written);
+        }
+        assert_eq!(parse_version("3.1").unwrap(), Version::new(3, 1, 0));
+        assert_eq!(
+            parse_version("3-rc.1").unwrap(),
+            Version::parse("3.0.0-rc.1").unwrap()
+        );
+        assert!(parse_version("3.x").is_err());
+    }
+
+    #[test]
+    fn rolls_calver_over_by_month() {
+        let today = Date::from_calendar_date(2026, time::Month::October, 14).unwrap();
+        for (input, expected) in [
+            ("2026.10.0", "2026.10.1"),
+            ("2026.10.3", "2026.10.4"),
+            ("2026.10.3-beta.1", "2026.10.4"),
+            ("2026.9.5", "2026.10.0"),
+            ("2025.10.2", "2026.10.0"),
+            ("1.4.0", "2026.10.0"),
+        ] {
+            let mut version = parse_version(input).unwrap();
+            bump_calver(&mut version, today);
+            assert_eq!(version.to_string(), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn writes_full_and_short_formats() {
+        // `version_format = "full"` asks for 3 components, "short" for 2
+        for (input, components, expected) in [
+            ("1.3", 3, "1.3.0"),
+            ("1", 3, "1.0.0"),
+            ("1.3.0", 2, "1.3"),
+            ("1.0.0", 2, "1.0"),
+            ("1.3.2", 2, "1.3.2"),
+            ("1.3.0-rc.1", 2, "1.3-rc.1"),
+            ("1.3-rc.1", 3, "1.3.0-rc.1"),
+        ] {
+            let mut version = ToolVersion::parse(input).unwrap();
+            version.components = components;
+            assert_eq!(version.to_string(), expected, "{input} {components}");
+        }
+    }
+
+    #[test]
+    fn keeps_lower_components() {
+        use BumpKind::{Major, Minor, Patch};
+        for (input, kind, expected) in [
+            ("1.3.2", Minor, "1.4.2"),
+            ("1.3.2", Major, "2.3.2"),
+            ("1.3.2", Patch, "1.3.3"),
+            ("1.3", Major, "2.3"),
+        ] {
+            assert_eq!(
+                changed(input, |v| bump_keep_lower(v, kind)),
+                expected,
+                "{input} {kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn promotes_along_the_channels() {
+        let channels = ["alpha", "beta", "rc"].map(String::from);
+        for (input, expected) in [
+            ("1.4.0-alpha.3", Ok("1.4.0-beta.1")),
+            ("1.4.0-beta.2", Ok("1.4.0-rc.1")),
+            ("1.4.0-rc.2", Ok("1.4.0")),
+            ("1.4-rc.1", Ok("1.4")),
+            (
+                "1.4.0",
+                Err("1.4.0 is not a pre-release, nothing to promote"),
+            ),
+            (
+                "1.4.0-dev.1",
+                Err("pre-release channel 'dev' is not one of alpha, beta, rc"),
+            ),
+        ] {
+            let mut version = ToolVersion::parse(input).unwrap();
+            let result = promote(&mut version.version, &channels).map(|_| version.to_string());
+            assert_eq!(
+                result,
+                expected.map(str::to_string).map_err(str::to_string),
+                "{input}"
+            );
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A project folder of its own for the test called `name`.
+fn project(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rnplug-cli-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("manifest.xml"),
+        "<?xml version=\"1.0\"?>\n<RenoiseScriptingTool doc_version=\"0\">\n  <ApiVersion>6</ApiVersion>\n  <Id>com.example.Tool</Id>\n  <Name>Tool</Name>\n  <Version>1.0</Version>\n  <Description>Test tool</Description>\n</RenoiseScriptingTool>\n",
+    )
+    .unwrap();
+    fs::write(dir.join("main.lua"), "print('hi')\n").unwrap();
+    dir
+}
+
+#[test]
+fn package_json_keeps_stdout_to_the_report() {
+    let dir = project("json");
+    fs::write(dir.join(".DS_Store"), "junk").unwrap();
+    fs::write(dir.join("main.lua~"), "junk").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rnplug"))
+        .args(["-v", "package", "--json", "--no-check-tags"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap_or_else(|e| {
+        panic!("stdout is not JSON ({e}):\n{stdout}");
+    });
+    assert!(report.is_object(), "{report}");
+    assert!(stderr.contains("Skipped 2 junk file(s)"), "{stderr}");
+    assert!(stderr.contains("adding main.lua"), "{stderr}");
+    fs::remove_dir_all(&dir).unwrap();
+}